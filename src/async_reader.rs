@@ -0,0 +1,261 @@
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use libc;
+use tokio::io::unix::AsyncFd;
+use tokio::io::Interest;
+
+use {Cursor, Event, KmsgReader, KmsgRecord, Position, Resume};
+
+/// Asynchronous reader of kernel log records from `/dev/kmsg`
+///
+/// Wraps a non-blocking [`KmsgReader`](struct.KmsgReader.html) registered
+/// with the tokio reactor and yields parsed records as a `Stream`. It has
+/// to be created from within a tokio runtime. Available with `tokio`
+/// feature.
+///
+/// ```rust,no_run,edition2018
+/// use std::future::poll_fn;
+/// use kernlog::{AsyncKmsgReader, Event, Position};
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() {
+///     let mut reader = AsyncKmsgReader::open().unwrap();
+///     reader.seek(Position::End).unwrap();
+///     reader.set_follow(true);
+///     while let Some(event) = poll_fn(|cx| reader.poll_read_event(cx)).await.unwrap() {
+///         match event {
+///             Event::Record(record) => println!("{}", record.message),
+///             Event::Lost(count) => eprintln!("lost {} records", count),
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct AsyncKmsgReader {
+    inner: AsyncFd<KmsgReader>,
+}
+
+impl AsyncKmsgReader {
+    /// Open `/dev/kmsg` for reading
+    pub fn open() -> io::Result<AsyncKmsgReader> {
+        AsyncKmsgReader::from_reader(KmsgReader::open()?)
+    }
+
+    /// Open device at `path` for reading kmsg records
+    pub fn open_path<P: AsRef<::std::path::Path>>(path: P) -> io::Result<AsyncKmsgReader> {
+        AsyncKmsgReader::from_reader(KmsgReader::open_path(path)?)
+    }
+
+    /// Register reader with the reactor, switching its file
+    /// into non-blocking mode
+    pub fn from_reader(reader: KmsgReader) -> io::Result<AsyncKmsgReader> {
+        let fd = reader.as_raw_fd();
+        let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+        if flags < 0 || unsafe { libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // the reader owns its file, so the descriptor stays valid
+        // for the reader's lifetime
+        let inner = unsafe { AsyncFd::register_with_interest(reader, Interest::READABLE)? };
+        Ok(AsyncKmsgReader { inner })
+    }
+
+    /// Set whether to wait for new records at the end of the buffer
+    /// instead of ending the stream there
+    pub fn set_follow(&mut self, follow: bool) {
+        self.inner.get_mut().set_follow(follow);
+    }
+
+    /// Set predicate records have to match to be read
+    pub fn set_filter<F: Fn(&KmsgRecord) -> bool + Send + 'static>(&mut self, filter: F) {
+        self.inner.get_mut().set_filter(filter);
+    }
+
+    /// Move reader to given position in the ring buffer
+    pub fn seek(&mut self, position: Position) -> io::Result<()> {
+        self.inner.get_mut().seek(position)
+    }
+
+    /// Cursor pointing after the last record read
+    pub fn cursor(&self) -> Option<Cursor> {
+        self.inner.get_ref().cursor()
+    }
+
+    /// Move reader right after the record pointed to by `cursor`,
+    /// see `KmsgReader::seek_cursor()`
+    pub fn seek_cursor(&mut self, cursor: &Cursor) -> io::Result<Resume> {
+        self.inner.get_mut().seek_cursor(cursor)
+    }
+
+    /// Get reference to the underlying blocking reader
+    pub fn get_ref(&self) -> &KmsgReader {
+        self.inner.get_ref()
+    }
+
+    /// Poll for the next record or lost records notification,
+    /// `None` is returned at the end of the buffer unless following
+    pub fn poll_read_event(&mut self, cx: &mut Context) -> Poll<io::Result<Option<Event>>> {
+        // a record held back after `seek_cursor()` or a lost records
+        // notification is there without the device becoming readable
+        match self.inner.get_mut().try_read_event() {
+            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => (),
+            result => return Poll::Ready(result),
+        }
+        // without following there is nothing to wait for, an empty
+        // buffer would never become readable
+        if !self.inner.get_ref().follows() {
+            return Poll::Ready(Ok(None));
+        }
+
+        loop {
+            let mut guard = match self.inner.poll_read_ready_mut(cx) {
+                Poll::Ready(guard) => guard?,
+                Poll::Pending => return Poll::Pending,
+            };
+
+            if let Ok(result) = guard.try_io(|inner| inner.get_mut().try_read_event()) {
+                return Poll::Ready(result);
+            }
+        }
+    }
+
+    /// Stream of events, records and lost records notifications
+    pub fn events(&mut self) -> AsyncEvents<'_> {
+        AsyncEvents(self)
+    }
+}
+
+impl Stream for AsyncKmsgReader {
+    type Item = io::Result<KmsgRecord>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<io::Result<KmsgRecord>>> {
+        let reader = self.get_mut();
+        loop {
+            match reader.poll_read_event(cx) {
+                Poll::Ready(Ok(Some(Event::Lost(_)))) => (),
+                Poll::Ready(Ok(Some(Event::Record(record)))) => return Poll::Ready(Some(Ok(record))),
+                Poll::Ready(Ok(None)) => return Poll::Ready(None),
+                Poll::Ready(Err(err)) => return Poll::Ready(Some(Err(err))),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Stream of kmsg reader events, see `AsyncKmsgReader::events()`
+#[derive(Debug)]
+pub struct AsyncEvents<'a>(&'a mut AsyncKmsgReader);
+
+impl<'a> Stream for AsyncEvents<'a> {
+    type Item = io::Result<Event>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<io::Result<Event>>> {
+        self.get_mut().0.poll_read_event(cx).map(Result::transpose)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::future::poll_fn;
+    use std::os::fd::OwnedFd;
+    use std::os::unix::net::UnixDatagram;
+    use std::task::Poll;
+
+    use tokio::runtime::{Builder, Runtime};
+
+    use super::AsyncKmsgReader;
+    use {Event, KmsgReader, Resume};
+
+    fn runtime() -> Runtime {
+        Builder::new_current_thread().enable_io().build().unwrap()
+    }
+
+    #[test]
+    fn read_events() {
+        let (tx, rx) = UnixDatagram::pair().unwrap();
+        for seq in &[1, 2, 5, 6] {
+            tx.send(format!("6,{},0,-;record {}\n", seq, seq).as_bytes()).unwrap();
+        }
+        rx.set_nonblocking(true).unwrap();
+
+        let runtime = runtime();
+        let _guard = runtime.enter();
+        let mut reader = AsyncKmsgReader::from_reader(KmsgReader::from_file(File::from(OwnedFd::from(rx)))).unwrap();
+        reader.set_filter(|record| record.sequence != 2);
+
+        let mut next = || runtime.block_on(poll_fn(|cx| reader.poll_read_event(cx))).unwrap();
+        assert!(matches!(next(), Some(Event::Record(ref r)) if r.message == "record 1"));
+        assert_eq!(next(), Some(Event::Lost(2)));
+        assert!(matches!(next(), Some(Event::Record(ref r)) if r.sequence == 5));
+        assert!(matches!(next(), Some(Event::Record(ref r)) if r.sequence == 6));
+        assert_eq!(next(), None);
+    }
+
+    #[test]
+    fn empty_buffer() {
+        let (_tx, rx) = UnixDatagram::pair().unwrap();
+
+        let runtime = runtime();
+        let _guard = runtime.enter();
+        let mut reader = AsyncKmsgReader::from_reader(KmsgReader::from_file(File::from(OwnedFd::from(rx)))).unwrap();
+        let event = runtime.block_on(poll_fn(|cx| reader.poll_read_event(cx))).unwrap();
+        assert_eq!(event, None);
+    }
+
+    #[test]
+    fn follow_new_records() {
+        let (tx, rx) = UnixDatagram::pair().unwrap();
+        rx.set_nonblocking(true).unwrap();
+
+        let runtime = runtime();
+        let _guard = runtime.enter();
+        let mut reader = AsyncKmsgReader::from_reader(KmsgReader::from_file(File::from(OwnedFd::from(rx)))).unwrap();
+        reader.set_follow(true);
+
+        let writer = ::std::thread::spawn(move || {
+            ::std::thread::sleep(::std::time::Duration::from_millis(50));
+            tx.send(b"3,7,0,-;late record\n").unwrap();
+        });
+        let event = runtime.block_on(poll_fn(|cx| reader.poll_read_event(cx))).unwrap();
+        assert!(matches!(event, Some(Event::Record(ref r)) if r.message == "late record"));
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn follow_after_cursor() {
+        let runtime = runtime();
+        let _guard = runtime.enter();
+        let mut reader = match AsyncKmsgReader::open() {
+            Ok(reader) => reader,
+            Err(_) => return,
+        };
+
+        let mut cursors = Vec::new();
+        while let Some(event) = runtime.block_on(poll_fn(|cx| reader.poll_read_event(cx))).unwrap() {
+            if let Event::Record(record) = event {
+                cursors.push((reader.cursor(), record.sequence));
+            }
+        }
+        let (cursor, sequence) = match cursors.len() {
+            len if len >= 2 => (cursors[len - 2].0.clone(), cursors[len - 1].1),
+            _ => return,
+        };
+        let cursor = match cursor {
+            Some(cursor) => cursor,
+            None => return,
+        };
+
+        // waiting on the drained device clears its readiness
+        reader.set_follow(true);
+        let _ = runtime.block_on(poll_fn(|cx| Poll::Ready(reader.poll_read_event(cx))));
+        assert_eq!(reader.seek_cursor(&cursor).unwrap(), Resume::Resumed);
+        let event = runtime.block_on(poll_fn(|cx| reader.poll_read_event(cx))).unwrap();
+        assert!(matches!(event, Some(Event::Record(ref r)) if r.sequence == sequence));
+    }
+}
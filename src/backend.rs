@@ -0,0 +1,43 @@
+use std::fmt;
+
+/// Default syslog daemon socket
+pub const SYSLOG_SOCKET: &str = "/dev/log";
+
+/// Default journald native protocol socket
+pub const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// Destination kernel logger writes records to
+///
+/// `KernelLogBuilder::backends()` sets the list of backends to try
+/// in order, the first one which can be opened is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Backend {
+    /// Kernel log device, `/dev/kmsg` by default
+    Kmsg,
+    /// Syslog daemon socket `/dev/log`, records are written with
+    /// RFC 3164 or RFC 5424 headers, see `SyslogFormat`
+    Syslog,
+    /// Journald native protocol socket, records are sent as structured
+    /// journal entries with priority, identifier, source location and
+    /// key-values fields
+    Journald,
+    /// Standard error, records are written with `<N>` prefixes
+    /// understood by systemd for service output
+    Stderr,
+}
+
+impl Backend {
+    /// All backends, in the order of preference
+    pub const ALL: [Backend; 4] = [Backend::Kmsg, Backend::Syslog, Backend::Journald, Backend::Stderr];
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Backend::Kmsg => "kmsg",
+            Backend::Syslog => "syslog",
+            Backend::Journald => "journald",
+            Backend::Stderr => "stderr",
+        })
+    }
+}
@@ -0,0 +1,385 @@
+use std::path::PathBuf;
+
+use kernlog::{Facility, Priority, Severity, DEFAULT_DEVICE};
+
+pub const USAGE: &str = "\
+Usage: kernlog [options]
+       kernlog write [write options] [message...]
+
+Print or follow the kernel ring buffer, or write messages into it.
+
+Options:
+  -D, --device <path>       read records from <path> (default /dev/kmsg)
+  -d, --show-delta          show time delta between printed messages
+  -f, --facility <list>     restrict output to given facilities
+  -g, --grep <text>         print only messages containing <text>
+  -J, --json                use JSON output format, as dmesg --json
+  -l, --level <list>        restrict output to given levels, err+ selects
+                            err and more severe levels, +err err and less severe
+  -L, --color[=<when>]      colorize messages (auto, always or never)
+  -S, --suspend             count suspend time into uptime for --ctime
+  -t, --notime              don't show any timestamp with messages
+  -T, --ctime               show human-readable timestamps
+  -w, --follow              wait for new messages
+  -W, --follow-new          wait and print only new messages
+  -x, --decode              decode facility and level to readable strings
+  -h, --help                display this help
+  -V, --version             display version
+
+Write options:
+  -D, --device <path>       write records to <path> (default /dev/kmsg)
+  -f, --facility <name>     facility of messages (default user)
+  -i, --id[=<id>]           log process id, of this process or given one
+  -p, --priority <prio>     priority of messages as level or facility.level
+                            (default user.notice), a <N> prefix of a message
+                            line overrides it
+  -t, --tag <tag>           identifier of messages (default kernlog)
+
+Message is taken from arguments, or from standard input line by line.
+";
+
+/// Timestamp shown before messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeFormat {
+    /// Seconds since boot
+    Monotonic,
+    /// Wall-clock date and time
+    Ctime,
+    /// No timestamp
+    Off,
+}
+
+/// When to colorize output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Auto,
+    Always,
+    Never,
+}
+
+/// Options of printing the ring buffer
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShowOptions {
+    pub device: PathBuf,
+    pub time: TimeFormat,
+    pub delta: bool,
+    pub decode: bool,
+    pub suspend: bool,
+    pub levels: Option<Vec<Severity>>,
+    pub facilities: Option<Vec<Facility>>,
+    pub grep: Option<String>,
+    pub follow: bool,
+    pub only_new: bool,
+    pub color: Color,
+    pub json: bool,
+}
+
+impl Default for ShowOptions {
+    fn default() -> ShowOptions {
+        ShowOptions {
+            device: PathBuf::from(DEFAULT_DEVICE),
+            time: TimeFormat::Monotonic,
+            delta: false,
+            decode: false,
+            suspend: false,
+            levels: None,
+            facilities: None,
+            grep: None,
+            follow: false,
+            only_new: false,
+            color: Color::Auto,
+            json: false,
+        }
+    }
+}
+
+/// Options of writing messages into the ring buffer
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriteOptions {
+    pub device: PathBuf,
+    pub priority: Priority,
+    pub tag: Option<String>,
+    pub pid: bool,
+    pub id: Option<u32>,
+    pub message: Vec<String>,
+}
+
+impl Default for WriteOptions {
+    fn default() -> WriteOptions {
+        WriteOptions {
+            device: PathBuf::from(DEFAULT_DEVICE),
+            priority: Priority::new(Facility::User, Severity::Notice),
+            tag: None,
+            pid: false,
+            id: None,
+            message: Vec::new(),
+        }
+    }
+}
+
+/// What to do
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Show(ShowOptions),
+    Write(WriteOptions),
+    Help,
+    Version,
+}
+
+/// Command line argument, with value of `--name=value` form split off
+struct Arg {
+    name: String,
+    value: Option<String>,
+    positional: bool,
+}
+
+impl Arg {
+    fn option(name: String, value: Option<String>) -> Arg {
+        Arg { name, value, positional: false }
+    }
+}
+
+/// Split arguments into options with their values, expanding `-abc`
+/// into `-a -b -c` and `-lerr` into `-l err`, arguments following
+/// the first non-option one or `--` are positional
+fn split_args<I: IntoIterator<Item = String>>(args: I, takes_value: fn(&str) -> bool) -> Vec<Arg> {
+    let mut result: Vec<Arg> = Vec::new();
+    let mut options = true;
+    for arg in args {
+        let expects_value = result.last().is_some_and(|last| !last.positional && last.value.is_none() && takes_value(&last.name));
+        if expects_value {
+            result.last_mut().unwrap().value = Some(arg);
+        } else if !options || arg == "-" || !arg.starts_with('-') {
+            options = false;
+            result.push(Arg { name: arg, value: None, positional: true });
+        } else if arg == "--" {
+            options = false;
+        } else if arg.starts_with("--") {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name.to_owned(), Some(value.to_owned())),
+                None => (arg, None),
+            };
+            result.push(Arg::option(name, value));
+        } else {
+            for (i, c) in arg[1..].char_indices() {
+                let name = format!("-{}", c);
+                if takes_value(&name) && i + 1 + c.len_utf8() < arg.len() {
+                    let value = arg[1 + i + c.len_utf8()..].to_owned();
+                    result.push(Arg::option(name, Some(value)));
+                    break;
+                }
+                result.push(Arg::option(name, None));
+            }
+        }
+    }
+    result
+}
+
+/// Take value of an option, failing if it's missing or if a flag has one
+fn option_value(arg: &Arg, takes_value: bool) -> Result<String, String> {
+    match arg.value {
+        Some(ref value) if takes_value => Ok(value.clone()),
+        None if takes_value => Err(format!("option {} requires an argument", arg.name)),
+        Some(ref value) => Err(format!("option {} doesn't take an argument: {}", arg.name, value)),
+        None => Ok(String::new()),
+    }
+}
+
+fn show_takes_value(name: &str) -> bool {
+    matches!(name, "-D" | "--device" | "-f" | "--facility" | "-g" | "--grep" | "-l" | "--level")
+}
+
+fn write_takes_value(name: &str) -> bool {
+    matches!(name, "-D" | "--device" | "-f" | "--facility" | "-p" | "--priority" | "-t" | "--tag")
+}
+
+/// Parse command line arguments, not including program name
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut args = args.into_iter().peekable();
+    if args.peek().is_some_and(|arg| arg == "write") {
+        args.next();
+        return parse_write(args);
+    }
+    parse_show(args)
+}
+
+fn parse_show<I: IntoIterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut opts = ShowOptions::default();
+    for arg in split_args(args, show_takes_value) {
+        if arg.positional {
+            return Err(format!("unexpected argument: {}", arg.name));
+        }
+        let value = match &*arg.name {
+            "-L" | "--color" => arg.value.clone().unwrap_or_else(|| "always".to_owned()),
+            name => option_value(&arg, show_takes_value(name))?,
+        };
+
+        match &*arg.name {
+            "-D" | "--device" => opts.device = PathBuf::from(value),
+            "-d" | "--show-delta" => opts.delta = true,
+            "-f" | "--facility" => opts.facilities = Some(parse_facilities(&value)?),
+            "-g" | "--grep" => opts.grep = Some(value),
+            "-J" | "--json" => opts.json = true,
+            "-l" | "--level" => opts.levels = Some(parse_levels(&value)?),
+            "-L" | "--color" => opts.color = match &*value {
+                "auto" => Color::Auto,
+                "always" => Color::Always,
+                "never" => Color::Never,
+                _ => return Err(format!("unsupported color mode: {}", value)),
+            },
+            "-S" | "--suspend" => opts.suspend = true,
+            "-t" | "--notime" => opts.time = TimeFormat::Off,
+            "-T" | "--ctime" => opts.time = TimeFormat::Ctime,
+            "-w" | "--follow" => opts.follow = true,
+            "-W" | "--follow-new" => {
+                opts.follow = true;
+                opts.only_new = true;
+            }
+            "-x" | "--decode" => opts.decode = true,
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            name => return Err(format!("unrecognized option: {}", name)),
+        }
+    }
+
+    Ok(Command::Show(opts))
+}
+
+fn parse_write<I: IntoIterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut opts = WriteOptions::default();
+    for arg in split_args(args, write_takes_value) {
+        if arg.positional {
+            opts.message.push(arg.name);
+            continue;
+        }
+        if let ("-i", _) | ("--id", None) = (&*arg.name, &arg.value) {
+            opts.pid = true;
+            continue;
+        }
+        let value = match &*arg.name {
+            "--id" => arg.value.clone().unwrap_or_default(),
+            name => option_value(&arg, write_takes_value(name))?,
+        };
+
+        match &*arg.name {
+            "-D" | "--device" => opts.device = PathBuf::from(value),
+            "-f" | "--facility" => {
+                opts.priority.facility = value.parse().map_err(|_| format!("unknown facility: {}", value))?;
+            }
+            "--id" => opts.id = Some(value.parse().map_err(|_| format!("invalid id: {}", value))?),
+            "-p" | "--priority" => opts.priority = parse_priority(&value, opts.priority.facility)?,
+            "-t" | "--tag" => opts.tag = Some(value),
+            "-h" | "--help" => return Ok(Command::Help),
+            name => return Err(format!("unrecognized option: {}", name)),
+        }
+    }
+
+    Ok(Command::Write(opts))
+}
+
+/// Parse priority given as numeric code, `facility.level` or `level`
+/// (with `facility` kept)
+fn parse_priority(value: &str, facility: Facility) -> Result<Priority, String> {
+    let invalid = || format!("invalid priority: {}", value);
+    if let Ok(code) = value.parse::<u16>() {
+        return Priority::from_code(code).ok_or_else(invalid);
+    }
+    let (facility, level) = match value.split_once('.') {
+        Some((facility, level)) => (facility.parse().map_err(|_| invalid())?, level),
+        None => (facility, value),
+    };
+    Ok(Priority::new(facility, level.parse().map_err(|_| invalid())?))
+}
+
+/// Parse comma separated list of levels, `err+` stands for `err` and
+/// more severe levels, `+err` for `err` and less severe ones
+fn parse_levels(list: &str) -> Result<Vec<Severity>, String> {
+    let mut levels = Vec::new();
+    for item in list.split(',').filter(|item| !item.is_empty()) {
+        let (name, range) = if let Some(name) = item.strip_suffix('+') {
+            (name, Some(true))
+        } else if let Some(name) = item.strip_prefix('+') {
+            (name, Some(false))
+        } else {
+            (item, None)
+        };
+        let level: Severity = name.parse().map_err(|_| format!("unknown level: {}", item))?;
+        levels.extend(Severity::ALL.iter().cloned().filter(|&l| match range {
+            Some(true) => l <= level,
+            Some(false) => l >= level,
+            None => l == level,
+        }));
+    }
+    Ok(levels)
+}
+
+fn parse_facilities(list: &str) -> Result<Vec<Facility>, String> {
+    list.split(',')
+        .filter(|item| !item.is_empty())
+        .map(|item| item.parse().map_err(|_| format!("unknown facility: {}", item)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use kernlog::{Facility, Priority, Severity};
+
+    use super::{parse, Color, Command, ShowOptions, TimeFormat, WriteOptions};
+
+    fn write(args: &[&str]) -> WriteOptions {
+        match parse(Some("write").iter().chain(args).map(|s| s.to_string())) {
+            Ok(Command::Write(opts)) => opts,
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    fn show(args: &[&str]) -> ShowOptions {
+        match parse(args.iter().map(|s| s.to_string())) {
+            Ok(Command::Show(opts)) => opts,
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_flags() {
+        let opts = show(&["-Tdw", "--json", "--color=never"]);
+        assert_eq!(opts.time, TimeFormat::Ctime);
+        assert!(opts.delta && opts.follow && opts.json && !opts.only_new);
+        assert_eq!(opts.color, Color::Never);
+        assert_eq!(show(&["-L"]).color, Color::Always);
+        assert_eq!(parse(vec!["-h".to_owned()]), Ok(Command::Help));
+        assert!(parse(vec!["--bogus".to_owned()]).is_err());
+        assert!(parse(vec!["--json=yes".to_owned()]).is_err());
+    }
+
+    #[test]
+    fn parse_values() {
+        let opts = show(&["-lerr+", "--facility", "kern,daemon", "-g", "usb 1-1", "-D/tmp/kmsg"]);
+        assert_eq!(opts.levels, Some(vec![Severity::Emergency, Severity::Alert, Severity::Critical, Severity::Error]));
+        assert_eq!(opts.facilities, Some(vec![Facility::Kern, Facility::Daemon]));
+        assert_eq!(opts.grep.as_deref(), Some("usb 1-1"));
+        assert_eq!(opts.device.to_str(), Some("/tmp/kmsg"));
+        assert_eq!(show(&["--level=+info,emerg"]).levels, Some(vec![Severity::Info, Severity::Debug, Severity::Emergency]));
+        assert!(parse(vec!["-l".to_owned()]).is_err());
+        assert!(parse(vec!["-l".to_owned(), "loud".to_owned()]).is_err());
+    }
+
+    #[test]
+    fn parse_write() {
+        let opts = write(&["-p", "daemon.err", "-t", "hook", "-i", "mounted", "-i", "root"]);
+        assert_eq!(opts.priority, Priority::new(Facility::Daemon, Severity::Error));
+        assert_eq!(opts.tag.as_deref(), Some("hook"));
+        assert!(opts.pid);
+        assert_eq!(opts.message, vec!["mounted", "-i", "root"]);
+
+        let opts = write(&["--facility=local3", "-pwarn", "--id=42", "--", "-x"]);
+        assert_eq!(opts.priority, Priority::new(Facility::Local3, Severity::Warning));
+        assert_eq!((opts.pid, opts.id), (false, Some(42)));
+        assert_eq!(opts.message, vec!["-x"]);
+
+        assert_eq!(write(&["-p", "30"]).priority, Priority::new(Facility::Daemon, Severity::Info));
+        assert_eq!(write(&[]), WriteOptions::default());
+        assert!(parse(vec!["write".to_owned(), "-p".to_owned(), "daemon.loud".to_owned()]).is_err());
+        assert!(parse(vec!["extra".to_owned()]).is_err());
+    }
+}
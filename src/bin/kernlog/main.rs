@@ -0,0 +1,102 @@
+//! dmesg-like command line tool printing the kernel ring buffer
+//! and logger-like tool writing into it, built with `cli` feature
+
+extern crate kernlog;
+extern crate libc;
+
+mod args;
+mod print;
+mod write;
+
+use std::env;
+use std::io::{self, Write};
+use std::process;
+
+use kernlog::{Event, KmsgReader, Position};
+
+use args::{Color, Command, ShowOptions};
+use print::Printer;
+
+fn main() {
+    let command = match args::parse(env::args().skip(1)) {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("kernlog: {}", err);
+            eprintln!("Try 'kernlog --help' for more information.");
+            process::exit(2);
+        }
+    };
+
+    let result = match command {
+        Command::Help => io::stdout().write_all(args::USAGE.as_bytes()),
+        Command::Version => writeln!(io::stdout(), "kernlog {}", env!("CARGO_PKG_VERSION")),
+        Command::Show(opts) => show(&opts),
+        Command::Write(opts) => write::run(&opts),
+    };
+
+    match result {
+        Ok(()) => (),
+        Err(ref err) if err.kind() == io::ErrorKind::BrokenPipe => (),
+        Err(err) => {
+            eprintln!("kernlog: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
+fn show(opts: &ShowOptions) -> io::Result<()> {
+    let mut reader = KmsgReader::open_path(&opts.device).map_err(|err| {
+        io::Error::new(err.kind(), format!("cannot open {}: {}", opts.device.display(), err))
+    })?;
+    if opts.only_new {
+        reader.seek(Position::End)?;
+    }
+    reader.set_follow(opts.follow);
+
+    let (levels, facilities, grep) = (opts.levels.clone(), opts.facilities.clone(), opts.grep.clone());
+    reader.set_filter(move |record| {
+        levels.as_ref().is_none_or(|levels| levels.contains(&record.severity))
+            && facilities.as_ref().is_none_or(|facilities| facilities.contains(&record.facility))
+            && grep.as_ref().is_none_or(|grep| record.message.contains(grep.as_str()))
+    });
+
+    let color = match opts.color {
+        Color::Always => true,
+        Color::Never => false,
+        Color::Auto => is_color_terminal(),
+    };
+
+    let mut printer = Printer::new(opts, color);
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+
+    for event in reader.events() {
+        let event = match event {
+            Ok(event) => event,
+            // one malformed record doesn't end the listing
+            Err(ref err) if err.kind() == io::ErrorKind::InvalidData => {
+                out.flush()?;
+                eprintln!("kernlog: skipping {}", err);
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        match event {
+            Event::Record(record) => printer.record(&mut out, &record)?,
+            Event::Lost(count) => {
+                out.flush()?;
+                eprintln!("kernlog: {} records lost", count);
+            }
+        }
+        if opts.follow {
+            out.flush()?;
+        }
+    }
+
+    printer.finish(&mut out)
+}
+
+fn is_color_terminal() -> bool {
+    let tty = unsafe { libc::isatty(libc::STDOUT_FILENO) } == 1;
+    tty && env::var_os("NO_COLOR").is_none() && env::var("TERM").is_ok_and(|term| term != "dumb")
+}
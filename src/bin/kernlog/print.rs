@@ -0,0 +1,248 @@
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Write};
+use std::mem;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use kernlog::{BootClock, Caller, KmsgRecord, Severity};
+use libc;
+
+use args::{ShowOptions, TimeFormat};
+
+const RESET: &str = "\x1b[0m";
+const TIME_COLOR: &str = "\x1b[32m";
+const DECODE_COLOR: &str = "\x1b[33m";
+
+/// Printer of kmsg records in dmesg text or JSON format
+pub struct Printer {
+    time: TimeFormat,
+    delta: bool,
+    decode: bool,
+    color: bool,
+    json: bool,
+    clock: BootClock,
+    last_timestamp: Option<u64>,
+    printed: usize,
+}
+
+impl Printer {
+    pub fn new(opts: &ShowOptions, color: bool) -> Printer {
+        Printer {
+            time: opts.time,
+            delta: opts.delta,
+            decode: opts.decode,
+            color: color && !opts.json,
+            json: opts.json,
+            clock: BootClock::new().suspend(opts.suspend),
+            last_timestamp: None,
+            printed: 0,
+        }
+    }
+
+    /// Print single record
+    pub fn record(&mut self, out: &mut dyn Write, record: &KmsgRecord) -> io::Result<()> {
+        if self.json {
+            self.json_record(out, record)?;
+        } else {
+            self.text_record(out, record)?;
+        }
+        self.last_timestamp = Some(record.timestamp);
+        self.printed += 1;
+        Ok(())
+    }
+
+    /// Finish output, closing JSON document
+    pub fn finish(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        if self.json {
+            if self.printed == 0 {
+                out.write_all(b"{\n   \"dmesg\": [")?;
+            }
+            out.write_all(b"\n   ]\n}\n")?;
+        }
+        out.flush()
+    }
+
+    fn text_record(&self, out: &mut dyn Write, record: &KmsgRecord) -> io::Result<()> {
+        let mut line = String::new();
+
+        if self.decode {
+            self.colored(&mut line, DECODE_COLOR, &format!("{:<6}:{:<7}:", record.facility.to_string(), record.severity.name()));
+            line.push(' ');
+        }
+
+        let mut stamp = match self.time {
+            TimeFormat::Monotonic => seconds(record.timestamp),
+            TimeFormat::Ctime => ctime(self.clock.record_time(record)),
+            TimeFormat::Off => String::new(),
+        };
+        if self.delta {
+            let delta = record.timestamp.saturating_sub(self.last_timestamp.unwrap_or(record.timestamp));
+            if !stamp.is_empty() {
+                stamp.push(' ');
+            }
+            let _ = write!(stamp, "<{}>", seconds(delta));
+        }
+        if !stamp.is_empty() {
+            line.push('[');
+            self.colored(&mut line, TIME_COLOR, &stamp);
+            line.push_str("] ");
+        }
+
+        self.colored(&mut line, severity_color(record.severity), &escape(&record.message));
+        writeln!(out, "{}", line)
+    }
+
+    fn json_record(&self, out: &mut dyn Write, record: &KmsgRecord) -> io::Result<()> {
+        if self.printed == 0 {
+            out.write_all(b"{\n   \"dmesg\": [\n      {\n")?;
+        } else {
+            out.write_all(b",{\n")?;
+        }
+
+        if self.decode {
+            writeln!(out, "         \"fac\": \"{}\",", record.facility)?;
+            writeln!(out, "         \"pri\": \"{}\",", record.severity.name())?;
+        } else {
+            writeln!(out, "         \"pri\": {},", record.priority().code())?;
+        }
+        if self.time != TimeFormat::Off {
+            writeln!(out, "         \"time\": {:>12},", seconds(record.timestamp))?;
+        }
+        match record.flags.caller {
+            Some(Caller::Thread(id)) => writeln!(out, "         \"caller\": \"T{}\",", id)?,
+            Some(Caller::Cpu(id)) => writeln!(out, "         \"caller\": \"C{}\",", id)?,
+            None => (),
+        }
+        write!(out, "         \"msg\": \"{}\"\n      }}", json_escape(&record.message))
+    }
+
+    fn colored(&self, line: &mut String, color: &str, text: &str) {
+        if self.color && !color.is_empty() {
+            line.push_str(color);
+            line.push_str(text);
+            line.push_str(RESET);
+        } else {
+            line.push_str(text);
+        }
+    }
+}
+
+fn severity_color(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Emergency | Severity::Alert => "\x1b[7;31m",
+        Severity::Critical => "\x1b[1;31m",
+        Severity::Error => "\x1b[31m",
+        Severity::Warning => "\x1b[1m",
+        Severity::Notice | Severity::Info | Severity::Debug => "",
+    }
+}
+
+/// Format microseconds as `sssss.uuuuuu` seconds
+fn seconds(micros: u64) -> String {
+    format!("{:>5}.{:06}", micros / 1_000_000, micros % 1_000_000)
+}
+
+/// Format wall-clock time in local time zone the way dmesg does
+fn ctime(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()) as libc::time_t;
+    let mut buf = [0u8; 64];
+    let len = unsafe {
+        let mut tm: libc::tm = mem::zeroed();
+        libc::localtime_r(&secs, &mut tm);
+        libc::strftime(buf.as_mut_ptr() as *mut libc::c_char, buf.len(),
+                       b"%a %b %e %H:%M:%S %Y\0".as_ptr() as *const libc::c_char, &tm)
+    };
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+/// Escape control characters except newlines and tabs as `\xNN`
+fn escape(message: &str) -> String {
+    let mut result = String::with_capacity(message.len());
+    for c in message.chars() {
+        if c.is_control() && c != '\n' && c != '\t' {
+            let mut bytes = [0; 4];
+            for b in c.encode_utf8(&mut bytes).bytes() {
+                let _ = write!(result, "\\x{:02x}", b);
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+fn json_escape(message: &str) -> String {
+    let mut result = String::with_capacity(message.len());
+    for c in message.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(result, "\\u{:04x}", c as u32);
+            }
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use kernlog::KmsgRecord;
+
+    use args::{ShowOptions, TimeFormat};
+    use super::Printer;
+
+    fn print(opts: &ShowOptions, color: bool) -> String {
+        let records = [
+            &b"6,1,1500000,-;first line\n"[..],
+            &b"11,2,2000250,-,caller=T42;quote \" and\\x1b esc\n SUBSYSTEM=usb\n"[..],
+        ];
+        let mut printer = Printer::new(opts, color);
+        let mut out = Vec::new();
+        for data in &records {
+            printer.record(&mut out, &KmsgRecord::parse(data).unwrap()).unwrap();
+        }
+        printer.finish(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn text_output() {
+        let opts = ShowOptions::default();
+        assert_eq!(print(&opts, false), "[    1.500000] first line\n[    2.000250] quote \" and\\x1b esc\n");
+
+        let opts = ShowOptions { time: TimeFormat::Off, delta: true, decode: true, ..ShowOptions::default() };
+        assert_eq!(print(&opts, false),
+            "kern  :info   : [<    0.000000>] first line\nuser  :err    : [<    0.500250>] quote \" and\\x1b esc\n");
+
+        assert_eq!(print(&ShowOptions::default(), true),
+            "[\x1b[32m    1.500000\x1b[0m] first line\n[\x1b[32m    2.000250\x1b[0m] \x1b[31mquote \" and\\x1b esc\x1b[0m\n");
+    }
+
+    #[test]
+    fn json_output() {
+        let opts = ShowOptions { json: true, ..ShowOptions::default() };
+        assert_eq!(print(&opts, true), r#"{
+   "dmesg": [
+      {
+         "pri": 6,
+         "time":     1.500000,
+         "msg": "first line"
+      },{
+         "pri": 11,
+         "time":     2.000250,
+         "caller": "T42",
+         "msg": "quote \" and\u001b esc"
+      }
+   ]
+}
+"#);
+
+        let mut printer = Printer::new(&opts, false);
+        let mut out = Vec::new();
+        printer.finish(&mut out).unwrap();
+        assert_eq!(out, b"{\n   \"dmesg\": [\n   ]\n}\n");
+    }
+}
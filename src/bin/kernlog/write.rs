@@ -0,0 +1,77 @@
+use std::io::{self, BufRead};
+
+use kernlog::{KernelLog, Priority};
+
+use args::WriteOptions;
+
+/// Write message from arguments, or lines read from stdin, into the ring buffer
+pub fn run(opts: &WriteOptions) -> io::Result<()> {
+    let mut builder = KernelLog::builder()
+        .device(&opts.device)
+        .pid(opts.pid)
+        .split_lines(true);
+    if let Some(ref tag) = opts.tag {
+        builder = builder.ident(tag.as_str());
+    }
+    if let Some(id) = opts.id {
+        let tag = opts.tag.clone().unwrap_or_else(|| "kernlog".to_owned());
+        builder = builder.ident(format!("{}[{}]", tag, id)).pid(false);
+    }
+    let logger = builder.build().map_err(|err| {
+        io::Error::new(err.kind(), format!("cannot open {}: {}", opts.device.display(), err))
+    })?;
+
+    if !opts.message.is_empty() {
+        let message = opts.message.join(" ");
+        let (priority, message) = split_priority(&message, opts.priority);
+        return logger.write_message(priority, message.as_bytes());
+    }
+
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let line = line?;
+        let (priority, message) = split_priority(&line, opts.priority);
+        if !message.is_empty() {
+            logger.write_message(priority, message.as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+/// Split `<N>` priority prefix off the message, a prefix without
+/// facility bits keeps facility of `default`
+fn split_priority(message: &str, default: Priority) -> (Priority, &str) {
+    let prefix = message.strip_prefix('<')
+        .and_then(|rest| rest.split_once('>'))
+        .and_then(|(code, rest)| {
+            if code.is_empty() || !code.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            let priority = Priority::from_code(code.parse().ok()?)?;
+            Some((priority, rest))
+        });
+
+    match prefix {
+        Some((priority, rest)) if priority.code() < 8 => (Priority::new(default.facility, priority.severity), rest),
+        Some((priority, rest)) => (priority, rest),
+        None => (default, message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kernlog::{Facility, Priority, Severity};
+
+    use super::split_priority;
+
+    #[test]
+    fn priority_prefix() {
+        let default = Priority::new(Facility::Daemon, Severity::Notice);
+        assert_eq!(split_priority("plain", default), (default, "plain"));
+        assert_eq!(split_priority("<3>failed", default), (Priority::new(Facility::Daemon, Severity::Error), "failed"));
+        assert_eq!(split_priority("<134>local", default), (Priority::new(Facility::Local0, Severity::Info), "local"));
+        assert_eq!(split_priority("<b>bold</b>", default), (default, "<b>bold</b>"));
+        assert_eq!(split_priority("<206>odd", default), (Priority::new(Facility::Other(25), Severity::Info), "odd"));
+        assert_eq!(split_priority("<1024>huge", default), (default, "<1024>huge"));
+    }
+}
@@ -0,0 +1,379 @@
+use std::env;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::AtomicUsize;
+
+use log::{Level, LevelFilter};
+
+use {Backend, Error, Facility, Filter, Formatter, KernelLog, Severity, SeverityMap, TargetFormatter};
+use {RateLimit, Sanitize, SyslogFormat, WritePolicy, DEFAULT_RECORD_MAX, JOURNALD_SOCKET, SYSLOG_SOCKET};
+use journald::Journal;
+use ratelimit::RateLimiter;
+use syslog::SyslogSocket;
+#[cfg(feature = "kv")]
+use KvFilter;
+
+/// Default kernel log device
+pub const DEFAULT_DEVICE: &str = "/dev/kmsg";
+
+/// Builder for a configured `KernelLog`
+///
+/// ```rust,no_run
+/// extern crate log;
+/// extern crate kernlog;
+///
+/// use log::LevelFilter;
+/// use kernlog::KernelLog;
+///
+/// fn main() {
+///     KernelLog::builder()
+///         .level(LevelFilter::Warn)
+///         .target_level("mycrate", LevelFilter::Debug)
+///         .ident("my-generator")
+///         .init()
+///         .unwrap();
+/// }
+/// ```
+pub struct KernelLogBuilder {
+    device: PathBuf,
+    backends: Vec<Backend>,
+    syslog: PathBuf,
+    syslog_format: SyslogFormat,
+    journald: PathBuf,
+    writer: Option<Box<dyn Write + Send>>,
+    filter: Filter,
+    severities: SeverityMap,
+    facility: Facility,
+    ident: Option<String>,
+    pid: bool,
+    formatter: Box<dyn Formatter>,
+    split_lines: bool,
+    continuation: Option<String>,
+    record_max: usize,
+    policy: WritePolicy,
+    sanitize: Sanitize,
+    rate_limit: Option<RateLimit>,
+    target_rate_limit: Option<RateLimit>,
+    #[cfg(feature = "kv")]
+    kv: KvFilter,
+}
+
+impl KernelLogBuilder {
+    /// Create new builder with default settings
+    pub fn new() -> KernelLogBuilder {
+        KernelLogBuilder {
+            device: PathBuf::from(DEFAULT_DEVICE),
+            backends: vec![Backend::Kmsg],
+            syslog: PathBuf::from(SYSLOG_SOCKET),
+            syslog_format: SyslogFormat::default(),
+            journald: PathBuf::from(JOURNALD_SOCKET),
+            writer: None,
+            filter: Filter::default(),
+            severities: SeverityMap::default(),
+            facility: Facility::User,
+            ident: default_ident(),
+            pid: true,
+            formatter: Box::new(TargetFormatter),
+            split_lines: false,
+            continuation: None,
+            record_max: DEFAULT_RECORD_MAX,
+            policy: WritePolicy::default(),
+            sanitize: Sanitize::default(),
+            rate_limit: None,
+            target_rate_limit: None,
+            #[cfg(feature = "kv")]
+            kv: KvFilter::default(),
+        }
+    }
+
+    /// Set path of the device to write log records to (`/dev/kmsg` by default)
+    pub fn device<P: AsRef<Path>>(mut self, path: P) -> KernelLogBuilder {
+        self.device = path.as_ref().to_path_buf();
+        self
+    }
+
+    /// Set backends to try in order, the first one which can be opened
+    /// is used (only `Backend::Kmsg` by default)
+    ///
+    /// `Backend::ALL` falls back from `/dev/kmsg` to syslog, journald
+    /// and finally stderr, which can always be opened.
+    pub fn backends<I: IntoIterator<Item = Backend>>(mut self, backends: I) -> KernelLogBuilder {
+        self.backends = backends.into_iter().collect();
+        self
+    }
+
+    /// Set path of the syslog daemon socket used by `Backend::Syslog`
+    /// (`/dev/log` by default)
+    ///
+    /// Both datagram and stream sockets are supported, the socket
+    /// is reconnected if the daemon was restarted.
+    pub fn syslog_socket<P: AsRef<Path>>(mut self, path: P) -> KernelLogBuilder {
+        self.syslog = path.as_ref().to_path_buf();
+        self
+    }
+
+    /// Set header format of messages written by `Backend::Syslog`
+    /// (`SyslogFormat::Rfc3164` by default)
+    pub fn syslog_format(mut self, format: SyslogFormat) -> KernelLogBuilder {
+        self.syslog_format = format;
+        self
+    }
+
+    /// Set path of the journald native protocol socket used by
+    /// `Backend::Journald` (`/run/systemd/journal/socket` by default)
+    pub fn journald_socket<P: AsRef<Path>>(mut self, path: P) -> KernelLogBuilder {
+        self.journald = path.as_ref().to_path_buf();
+        self
+    }
+
+    /// Write log records to already opened sink instead of opening device
+    pub fn writer<W: Write + Send + 'static>(mut self, writer: W) -> KernelLogBuilder {
+        self.writer = Some(Box::new(writer));
+        self
+    }
+
+    /// Set default level filter for records (`Info` by default)
+    pub fn level(mut self, filter: LevelFilter) -> KernelLogBuilder {
+        self.filter.set_level(filter);
+        self
+    }
+
+    /// Set level filter for records with targets starting with `target`
+    ///
+    /// The longest matching target wins, records not matching
+    /// any target use the default level filter.
+    pub fn target_level<S: Into<String>>(mut self, target: S, filter: LevelFilter) -> KernelLogBuilder {
+        self.filter.add_directive(target, filter);
+        self
+    }
+
+    /// Replace default level and all target levels with `filter`
+    ///
+    /// Filters are usually parsed from `env_logger` like directives,
+    /// e.g. `"mycrate=debug,hyper=warn,info".parse()`.
+    pub fn filter(mut self, filter: Filter) -> KernelLogBuilder {
+        self.filter = filter;
+        self
+    }
+
+    /// Set severity to emit records with given level as
+    pub fn severity(mut self, level: Level, severity: Severity) -> KernelLogBuilder {
+        self.severities.set(level, severity);
+        self
+    }
+
+    /// Replace mapping of all levels to severities
+    pub fn severities(mut self, severities: SeverityMap) -> KernelLogBuilder {
+        self.severities = severities;
+        self
+    }
+
+    /// Set facility to mark records with (`user` by default)
+    pub fn facility(mut self, facility: Facility) -> KernelLogBuilder {
+        self.facility = facility;
+        self
+    }
+
+    /// Set program identifier to prefix every message with
+    /// (executable name by default)
+    pub fn ident<S: Into<String>>(mut self, ident: S) -> KernelLogBuilder {
+        self.ident = Some(ident.into());
+        self
+    }
+
+    /// Don't prefix messages with program identifier and process id
+    pub fn no_ident(mut self) -> KernelLogBuilder {
+        self.ident = None;
+        self
+    }
+
+    /// Set whether to add process id to program identifier
+    /// as in `ident[pid]: ` (enabled by default)
+    pub fn pid(mut self, pid: bool) -> KernelLogBuilder {
+        self.pid = pid;
+        self
+    }
+
+    /// Set formatter of message bodies (`TargetFormatter` by default)
+    pub fn formatter<F: Formatter + 'static>(mut self, formatter: F) -> KernelLogBuilder {
+        self.formatter = Box::new(formatter);
+        self
+    }
+
+    /// Set whether to split multi-line messages into separate records
+    ///
+    /// Every line is written with the same priority and identifier,
+    /// so continuation lines are not misattributed by `dmesg`.
+    pub fn split_lines(mut self, split: bool) -> KernelLogBuilder {
+        self.split_lines = split;
+        self
+    }
+
+    /// Set marker to prefix continuation lines of split messages with
+    pub fn continuation_marker<S: Into<String>>(mut self, marker: S) -> KernelLogBuilder {
+        self.continuation = Some(marker.into());
+        self
+    }
+
+    /// Set maximum length of a single written record, including priority
+    /// prefix and trailing newline (`DEFAULT_RECORD_MAX` by default)
+    ///
+    /// The limit is lowered automatically if the kernel refuses to accept
+    /// records of this length.
+    pub fn max_record_len(mut self, len: usize) -> KernelLogBuilder {
+        self.record_max = len;
+        self
+    }
+
+    /// Set what to do with records longer than the limit
+    /// (split them into fragments by default)
+    pub fn write_policy(mut self, policy: WritePolicy) -> KernelLogBuilder {
+        self.policy = policy;
+        self
+    }
+
+    /// Set how to sanitize control characters and invalid UTF-8
+    /// in messages (escape them as `\xNN` by default)
+    pub fn sanitize(mut self, sanitize: Sanitize) -> KernelLogBuilder {
+        self.sanitize = sanitize;
+        self
+    }
+
+    /// Limit rate of records written by all targets together
+    /// (unlimited by default)
+    ///
+    /// Records exceeding the limit are dropped, and once logging resumes
+    /// a single `N messages suppressed from <target>` warning is written
+    /// for each target, like the kernel's "callbacks suppressed" notes.
+    pub fn rate_limit(mut self, limit: RateLimit) -> KernelLogBuilder {
+        self.rate_limit = Some(limit);
+        self
+    }
+
+    /// Limit rate of records written by each target separately
+    /// (unlimited by default), see `rate_limit()`
+    pub fn target_rate_limit(mut self, limit: RateLimit) -> KernelLogBuilder {
+        self.target_rate_limit = Some(limit);
+        self
+    }
+
+    /// Set which record key-values to append to messages
+    /// (all of them by default)
+    ///
+    /// `priority` and `facility` keys are never appended, they
+    /// override record severity and facility instead.
+    #[cfg(feature = "kv")]
+    pub fn kv_filter(mut self, filter: KvFilter) -> KernelLogBuilder {
+        self.kv = filter;
+        self
+    }
+
+    /// Build configured kernel logger
+    ///
+    /// Fails with the error of the first backend if none of them
+    /// can be opened.
+    pub fn build(self) -> io::Result<KernelLog> {
+        let (kmsg, backend) = match self.writer {
+            Some(writer) => (writer, None),
+            None => {
+                let mut error = None;
+                let mut opened = None;
+                for &backend in &self.backends {
+                    match self.open(backend) {
+                        Ok(kmsg) => {
+                            opened = Some((kmsg, Some(backend)));
+                            break;
+                        }
+                        Err(err) => {
+                            error.get_or_insert(err);
+                        }
+                    }
+                }
+                match opened {
+                    Some(opened) => opened,
+                    None => return Err(error.unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no backends"))),
+                }
+            }
+        };
+        Ok(KernelLog {
+            kmsg: Mutex::new(kmsg),
+            backend,
+            syslog_format: self.syslog_format,
+            filter: self.filter,
+            severities: self.severities,
+            facility: self.facility,
+            ident: self.ident,
+            pid: self.pid,
+            formatter: self.formatter,
+            split_lines: self.split_lines,
+            continuation: self.continuation,
+            record_max: AtomicUsize::new(self.record_max),
+            policy: self.policy,
+            sanitize: self.sanitize,
+            ratelimit: if self.rate_limit.is_some() || self.target_rate_limit.is_some() {
+                Some(Mutex::new(RateLimiter::new(self.rate_limit, self.target_rate_limit)))
+            } else {
+                None
+            },
+            #[cfg(feature = "kv")]
+            kv: self.kv,
+        })
+    }
+
+    fn open(&self, backend: Backend) -> io::Result<Box<dyn Write + Send>> {
+        Ok(match backend {
+            Backend::Kmsg => Box::new(OpenOptions::new().append(true).open(&self.device)?),
+            Backend::Syslog => Box::new(SyslogSocket::connect(&self.syslog)?),
+            Backend::Journald => Box::new(Journal::connect(&self.journald)?),
+            Backend::Stderr => Box::new(io::stderr()),
+        })
+    }
+
+    /// Build configured kernel logger and set it up as a default logger
+    pub fn init(self) -> Result<(), Error> {
+        self.build()?.install()
+    }
+}
+
+/// Executable name the program was invoked with, like
+/// `program_invocation_short_name` syslog uses by default
+fn default_ident() -> Option<String> {
+    env::args_os().next()
+        .map(PathBuf::from)
+        .or_else(|| env::current_exe().ok())
+        .and_then(|path| path.file_name().map(|name| name.to_string_lossy().into_owned()))
+}
+
+impl fmt::Debug for KernelLogBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("KernelLogBuilder")
+            .field("device", &self.device)
+            .field("backends", &self.backends)
+            .field("syslog", &self.syslog)
+            .field("syslog_format", &self.syslog_format)
+            .field("journald", &self.journald)
+            .field("writer", &self.writer.as_ref().map(|_| "..."))
+            .field("filter", &self.filter)
+            .field("severities", &self.severities)
+            .field("facility", &self.facility)
+            .field("ident", &self.ident)
+            .field("pid", &self.pid)
+            .field("split_lines", &self.split_lines)
+            .field("continuation", &self.continuation)
+            .field("record_max", &self.record_max)
+            .field("policy", &self.policy)
+            .field("sanitize", &self.sanitize)
+            .field("rate_limit", &self.rate_limit)
+            .field("target_rate_limit", &self.target_rate_limit)
+            .finish()
+    }
+}
+
+impl Default for KernelLogBuilder {
+    fn default() -> KernelLogBuilder {
+        KernelLogBuilder::new()
+    }
+}
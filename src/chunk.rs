@@ -0,0 +1,132 @@
+/// Default maximum length of a single record written to `/dev/kmsg`,
+/// including priority prefix and trailing newline
+///
+/// This is the kernel's `LOG_LINE_MAX`, 1024 bytes less 32 bytes reserved
+/// for the record prefix, longer writes fail with `EINVAL`. Kernels built
+/// with `CONFIG_PRINTK_CALLER` reserve 48 bytes, limiting records to 976
+/// bytes, the limit is lowered to it after the first rejected record.
+pub const DEFAULT_RECORD_MAX: usize = 1024 - 32;
+
+/// Lower bound of the record length limit when adapting it to the kernel
+pub const MIN_RECORD_MAX: usize = 128;
+
+/// What to do with records longer than the record length limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WritePolicy {
+    /// Split record into numbered fragments, `[1/3] `, `[2/3] ` and so on,
+    /// each written with the full priority and identifier prefix (default)
+    #[default]
+    Split,
+    /// Write only as much of the record as fits into the limit
+    Truncate,
+}
+
+/// Split `line` into chunks fitting into `limit` bytes together with
+/// `header_len` bytes of header, fragment marker and newline, never
+/// splitting UTF-8 encoded characters or `\xNN` escapes
+///
+/// Fragments are sized so that none of them, marker included, is longer
+/// than the first one: once the first fragment is accepted by the kernel,
+/// the rest are too, and fragment numbering never changes midway.
+pub(crate) fn split(line: &[u8], header_len: usize, limit: usize, policy: WritePolicy) -> Vec<&[u8]> {
+    let room = limit.saturating_sub(header_len + 1);
+    if line.len() <= room {
+        return vec![line];
+    }
+    if policy == WritePolicy::Truncate {
+        return vec![&line[..boundary(line, room.max(4))]];
+    }
+
+    // digits of the fragment count assumed for `[k/n] ` markers
+    let mut digits = 1;
+    loop {
+        let marker_len = |n: usize| n.to_string().len() + digits + 4;
+        let mut chunks = Vec::new();
+        let mut rest = line;
+        let mut first_len = room;
+        while !rest.is_empty() {
+            let room = first_len.saturating_sub(marker_len(chunks.len() + 1)).max(4);
+            let end = if rest.len() <= room { rest.len() } else { boundary(rest, room) };
+            if chunks.is_empty() {
+                first_len = end + marker_len(1);
+            }
+            chunks.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+
+        if chunks.len().to_string().len() <= digits {
+            return chunks;
+        }
+        digits += 1;
+    }
+}
+
+/// Closest chunk boundary at or before `index`, not splitting
+/// UTF-8 encoded characters or `\xNN` escapes
+fn boundary(bytes: &[u8], index: usize) -> usize {
+    let index = char_boundary(bytes, index);
+    (index.saturating_sub(3)..index)
+        .find(|&i| i > 0 && bytes[i] == b'\\' && bytes.get(i + 1) == Some(&b'x'))
+        .unwrap_or(index)
+}
+
+/// Closest UTF-8 character boundary at or before `index`,
+/// or `index` itself if there is none (the data is not UTF-8)
+fn char_boundary(bytes: &[u8], index: usize) -> usize {
+    (index.saturating_sub(3)..=index).rev()
+        .find(|&i| bytes[i] & 0xc0 != 0x80)
+        .filter(|&i| i > 0)
+        .unwrap_or(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{split, WritePolicy};
+
+    #[test]
+    fn fits() {
+        assert_eq!(split(b"hello", 5, 11, WritePolicy::Split), vec![&b"hello"[..]]);
+    }
+
+    #[test]
+    fn split_on_char_boundaries() {
+        let line = "ab\u{44f}\u{44f}\u{44f}cdefghijklmnopqrstuvwxyz".repeat(2);
+        let chunks = split(line.as_bytes(), 4, 40, WritePolicy::Split);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= 29));
+        assert!(chunks.iter().all(|chunk| ::std::str::from_utf8(chunk).is_ok()));
+        assert_eq!(chunks.concat(), line.as_bytes());
+    }
+
+    #[test]
+    fn first_fragment_longest() {
+        let line = "x".repeat(1000);
+        let chunks = split(line.as_bytes(), 10, 100, WritePolicy::Split);
+        let total = chunks.len();
+        assert_eq!(total, 13);
+        let lens = chunks.iter().enumerate()
+            .map(|(n, chunk)| format!("[{}/{}] ", n + 1, total).len() + chunk.len())
+            .collect::<Vec<_>>();
+        assert_eq!(lens[0], 89);
+        assert!(lens.iter().all(|&len| len <= lens[0]));
+        assert_eq!(chunks.concat(), line.as_bytes());
+    }
+
+    #[test]
+    fn keep_escapes() {
+        let line = br"0123456789\x1b[0m0123456789";
+        let chunks = split(line, 0, 14, WritePolicy::Truncate);
+        assert_eq!(chunks, vec![&b"0123456789"[..]]);
+        for limit in 18..24 {
+            let chunks = split(line, 0, limit, WritePolicy::Split);
+            assert!(chunks.iter().all(|chunk| !chunk[chunk.len().saturating_sub(3)..].contains(&b'\\')));
+            assert_eq!(chunks.concat(), &line[..]);
+        }
+    }
+
+    #[test]
+    fn truncate() {
+        let chunks = split("0123456789\u{44f}abc".as_bytes(), 4, 16, WritePolicy::Truncate);
+        assert_eq!(chunks, vec!["0123456789".as_bytes()]);
+    }
+}
@@ -0,0 +1,142 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use libc;
+
+use KmsgRecord;
+
+/// Default interval of boot time recomputation
+pub const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Converter of kmsg record timestamps to wall-clock time
+///
+/// Records are stamped with microseconds since boot, the boot time is
+/// computed as `CLOCK_REALTIME` less `CLOCK_MONOTONIC` (or `CLOCK_BOOTTIME`
+/// when accounting for suspend time). Wall clock can be stepped by NTP or
+/// an administrator, so the boot time is recomputed once per refresh
+/// interval.
+///
+/// ```rust,no_run
+/// extern crate kernlog;
+///
+/// use kernlog::{BootClock, KmsgReader};
+///
+/// fn main() {
+///     let clock = BootClock::new();
+///     for record in KmsgReader::open().unwrap() {
+///         let record = record.unwrap();
+///         println!("{:?} {}", clock.record_time(&record), record.message);
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct BootClock {
+    suspend: bool,
+    refresh: Duration,
+    boot_time: Mutex<Option<(SystemTime, Instant)>>,
+}
+
+impl BootClock {
+    /// Create clock ignoring suspend time, which matches timestamps
+    /// of records logged since the last resume
+    pub fn new() -> BootClock {
+        BootClock {
+            suspend: false,
+            refresh: DEFAULT_REFRESH_INTERVAL,
+            boot_time: Mutex::new(None),
+        }
+    }
+
+    /// Set whether to count time spent in suspend into uptime
+    ///
+    /// Kernel timestamps stop while the system is suspended, so this
+    /// matches timestamps of records logged before the first suspend.
+    pub fn suspend(mut self, suspend: bool) -> BootClock {
+        self.suspend = suspend;
+        self
+    }
+
+    /// Set how often to recompute boot time
+    pub fn refresh_interval(mut self, refresh: Duration) -> BootClock {
+        self.refresh = refresh;
+        self
+    }
+
+    /// Recompute boot time on the next conversion
+    pub fn refresh(&self) {
+        *self.boot_time.lock().unwrap() = None;
+    }
+
+    /// Wall-clock time of the boot
+    pub fn boot_time(&self) -> SystemTime {
+        let mut boot_time = self.boot_time.lock().unwrap();
+        match *boot_time {
+            Some((time, computed)) if computed.elapsed() < self.refresh => time,
+            _ => {
+                let time = compute_boot_time(self.suspend);
+                *boot_time = Some((time, Instant::now()));
+                time
+            }
+        }
+    }
+
+    /// Convert kmsg timestamp in microseconds since boot to wall-clock time
+    pub fn to_system_time(&self, timestamp: u64) -> SystemTime {
+        self.boot_time() + Duration::from_micros(timestamp)
+    }
+
+    /// Wall-clock time record was logged at
+    pub fn record_time(&self, record: &KmsgRecord) -> SystemTime {
+        self.to_system_time(record.timestamp)
+    }
+}
+
+impl Default for BootClock {
+    fn default() -> BootClock {
+        BootClock::new()
+    }
+}
+
+fn compute_boot_time(suspend: bool) -> SystemTime {
+    let uptime = clock_time(if suspend { libc::CLOCK_BOOTTIME } else { libc::CLOCK_MONOTONIC });
+    let now = clock_time(libc::CLOCK_REALTIME);
+    UNIX_EPOCH + now.checked_sub(uptime).unwrap_or_default()
+}
+
+fn clock_time(clock: libc::clockid_t) -> Duration {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // fails only for clocks unsupported by the kernel,
+    // and these are available since Linux 2.6.39
+    unsafe { libc::clock_gettime(clock, &mut ts) };
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use libc;
+
+    use super::{clock_time, BootClock};
+
+    #[test]
+    fn boot_time() {
+        let clock = BootClock::new();
+        let boot_time = clock.boot_time();
+        assert!(boot_time < SystemTime::now());
+        assert_eq!(clock.to_system_time(1_500_000), boot_time + Duration::from_millis(1500));
+
+        // time spent in suspend moves boot time back
+        assert!(BootClock::new().suspend(true).boot_time() <= boot_time + Duration::from_millis(10));
+    }
+
+    #[test]
+    fn convert_timestamps() {
+        let clock = BootClock::new().refresh_interval(Duration::from_secs(0));
+        let timestamp = clock_time(libc::CLOCK_MONOTONIC).as_micros() as u64;
+        let now = SystemTime::now();
+        let time = clock.to_system_time(timestamp);
+        let diff = time.duration_since(now).or_else(|e| Ok::<_, ()>(e.duration())).unwrap();
+        assert!(diff < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,97 @@
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::str::FromStr;
+
+/// Path to the random id generated by the kernel on every boot
+pub const BOOT_ID_PATH: &str = "/proc/sys/kernel/random/boot_id";
+
+/// Read id of the current boot
+pub fn boot_id() -> io::Result<String> {
+    fs::read_to_string(BOOT_ID_PATH).map(|id| id.trim().to_owned())
+}
+
+/// Position of a kmsg reader, which stays valid across reader restarts
+///
+/// Sequence numbers of kmsg records are reset on every boot, so the
+/// cursor is made of the boot id and the sequence number of the last
+/// record read. It's serialized as `boot_id:sequence`:
+///
+/// ```rust
+/// extern crate kernlog;
+///
+/// use kernlog::Cursor;
+///
+/// fn main() {
+///     let cursor: Cursor = "8d490ae7-5087-4b74-b3a1-6d552f0ea54b:1337".parse().unwrap();
+///     assert_eq!(cursor.sequence, 1337);
+///     assert_eq!(cursor.to_string(), "8d490ae7-5087-4b74-b3a1-6d552f0ea54b:1337");
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cursor {
+    /// Id of the boot the record was logged in
+    pub boot_id: String,
+    /// Sequence number of the last record read
+    pub sequence: u64,
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.boot_id, self.sequence)
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = ParseCursorError;
+
+    fn from_str(s: &str) -> Result<Cursor, ParseCursorError> {
+        let (boot_id, sequence) = s.trim().rsplit_once(':').ok_or(ParseCursorError)?;
+        if boot_id.is_empty() {
+            return Err(ParseCursorError);
+        }
+        Ok(Cursor {
+            boot_id: boot_id.to_owned(),
+            sequence: sequence.parse().map_err(|_| ParseCursorError)?,
+        })
+    }
+}
+
+/// Outcome of resuming a kmsg reader from a cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    /// Reader continues right after the cursor
+    Resumed,
+    /// System was rebooted since the cursor was taken, reader
+    /// starts from the beginning of the buffer
+    BootChanged,
+    /// Given number of records following the cursor were overwritten
+    /// in the ring buffer, reader continues with the oldest available one
+    Overwritten(u64),
+}
+
+/// Error returned when a cursor can't be parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseCursorError;
+
+impl fmt::Display for ParseCursorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid kmsg cursor, expected boot_id:sequence")
+    }
+}
+
+impl error::Error for ParseCursorError {}
+
+#[cfg(test)]
+mod tests {
+    use super::Cursor;
+
+    #[test]
+    fn parse_cursor() {
+        assert_eq!("boot:42\n".parse(), Ok(Cursor { boot_id: "boot".to_owned(), sequence: 42 }));
+        assert!("boot".parse::<Cursor>().is_err());
+        assert!(":42".parse::<Cursor>().is_err());
+        assert!("boot:-1".parse::<Cursor>().is_err());
+    }
+}
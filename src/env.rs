@@ -0,0 +1,152 @@
+use std::env;
+use std::fs;
+
+use log::LevelFilter;
+
+use {Filter, ParseFilterError, Severity};
+
+/// Environment variable with filter directives
+pub const ENV_VAR: &str = "KERNLOG";
+
+/// Kernel command line parameter with filter directives
+pub const CMDLINE_PARAM: &str = "kernlog";
+
+/// Path to the kernel command line
+pub const CMDLINE_PATH: &str = "/proc/cmdline";
+
+impl Filter {
+    /// Build filter from the kernel command line and environment
+    ///
+    /// Sources are applied in this order, later ones overriding earlier:
+    ///
+    /// 1. `debug` and `systemd.log_level=` kernel command line parameters,
+    /// 2. `SYSTEMD_LOG_LEVEL` environment variable,
+    /// 3. `kernlog=` kernel command line parameter,
+    /// 4. `KERNLOG` environment variable.
+    ///
+    /// systemd levels may be syslog level names (`err`, `warning`, ...)
+    /// or numbers, the `kernlog` ones use the usual directive syntax.
+    /// A missing `/proc/cmdline` is treated as empty command line.
+    ///
+    /// Invalid levels and directives are skipped like `env_logger` does,
+    /// so a typo on the shared kernel command line doesn't disable
+    /// logging, see `from_env_with_errors()` to report them.
+    pub fn from_env() -> Filter {
+        Filter::from_env_with_errors().0
+    }
+
+    /// Build filter from the kernel command line and environment like
+    /// `from_env()`, returning errors of skipped levels and directives
+    pub fn from_env_with_errors() -> (Filter, Vec<ParseFilterError>) {
+        let cmdline = fs::read_to_string(CMDLINE_PATH).unwrap_or_default();
+        from_sources(&cmdline, env::var("SYSTEMD_LOG_LEVEL").ok(), env::var(ENV_VAR).ok())
+    }
+}
+
+fn from_sources(cmdline: &str, systemd_env: Option<String>, kernlog_env: Option<String>) -> (Filter, Vec<ParseFilterError>) {
+    let mut filter = Filter::default();
+    let mut errors = Vec::new();
+    let params = parse_cmdline(cmdline);
+
+    for (name, value) in &params {
+        match (name.as_str(), value) {
+            ("debug", None) => filter.set_level(LevelFilter::Debug),
+            ("systemd.log_level", Some(level)) => match parse_syslog_level(level) {
+                Ok(level) => filter.set_level(level),
+                Err(err) => errors.push(err),
+            },
+            _ => (),
+        }
+    }
+
+    if let Some(level) = systemd_env {
+        match parse_syslog_level(&level) {
+            Ok(level) => filter.set_level(level),
+            Err(err) => errors.push(err),
+        }
+    }
+
+    for (name, value) in &params {
+        if let (CMDLINE_PARAM, Some(spec)) = (name.as_str(), value) {
+            errors.extend(filter.apply(spec));
+        }
+    }
+
+    if let Some(spec) = kernlog_env {
+        errors.extend(filter.apply(&spec));
+    }
+
+    (filter, errors)
+}
+
+/// Split kernel command line into `name[=value]` parameters,
+/// honoring double quotes the same way the kernel does
+fn parse_cmdline(cmdline: &str) -> Vec<(String, Option<String>)> {
+    let mut params = Vec::new();
+    let mut param = String::new();
+    let mut quoted = false;
+
+    for c in cmdline.chars().chain(Some(' ')) {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !param.is_empty() {
+                    let mut parts = param.splitn(2, '=');
+                    let name = parts.next().unwrap_or("").to_owned();
+                    params.push((name, parts.next().map(str::to_owned)));
+                    param.clear();
+                }
+            }
+            c => param.push(c),
+        }
+    }
+
+    params
+}
+
+fn parse_syslog_level(level: &str) -> Result<LevelFilter, ParseFilterError> {
+    level.parse::<Severity>()
+        .map(|severity| severity.to_level().to_level_filter())
+        .map_err(|_| ParseFilterError(level.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use log::LevelFilter;
+
+    use super::{from_sources, parse_cmdline};
+    use ParseFilterError;
+
+    #[test]
+    fn cmdline_params() {
+        let params = parse_cmdline("ro quiet kernlog=\"app=debug,warn\" root=/dev/sda1\n");
+        assert_eq!(params, vec![
+            ("ro".to_owned(), None),
+            ("quiet".to_owned(), None),
+            ("kernlog".to_owned(), Some("app=debug,warn".to_owned())),
+            ("root".to_owned(), Some("/dev/sda1".to_owned())),
+        ]);
+    }
+
+    #[test]
+    fn sources_precedence() {
+        let filter = from_sources("ro debug", None, None).0;
+        assert_eq!(filter.level(), LevelFilter::Debug);
+
+        let filter = from_sources("debug systemd.log_level=warning", None, None).0;
+        assert_eq!(filter.level(), LevelFilter::Warn);
+
+        let filter = from_sources("systemd.log_level=warning kernlog=app=trace", Some("err".to_owned()), None).0;
+        assert_eq!(filter.level(), LevelFilter::Error);
+        assert_eq!(filter.level_for("app"), LevelFilter::Trace);
+
+        let filter = from_sources("kernlog=app=trace,info", None, Some("app=off".to_owned())).0;
+        assert_eq!(filter.level(), LevelFilter::Info);
+        assert_eq!(filter.level_for("app"), LevelFilter::Off);
+
+        let (filter, errors) = from_sources("systemd.log_level=loud kernlog=app=trace,db=loud", None, Some("warn".to_owned()));
+        assert_eq!(filter.level(), LevelFilter::Warn);
+        assert_eq!(filter.level_for("app"), LevelFilter::Trace);
+        assert_eq!(errors, vec![ParseFilterError("loud".to_owned()), ParseFilterError("db=loud".to_owned())]);
+    }
+}
@@ -0,0 +1,44 @@
+use std::error;
+use std::fmt;
+use std::io;
+
+use log::SetLoggerError;
+
+/// Errors that may occur while setting up the kernel logger
+#[derive(Debug)]
+pub enum Error {
+    /// Kernel log device could not be opened or written
+    Io(io::Error),
+    /// Another logger has already been installed
+    SetLogger(SetLoggerError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref err) => write!(f, "failed to open kernel log: {}", err),
+            Error::SetLogger(ref err) => write!(f, "failed to set logger: {}", err),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref err) => Some(err),
+            Error::SetLogger(ref err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl From<SetLoggerError> for Error {
+    fn from(err: SetLoggerError) -> Error {
+        Error::SetLogger(err)
+    }
+}
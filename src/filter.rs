@@ -0,0 +1,163 @@
+use std::cmp;
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use log::{LevelFilter, Metadata};
+
+/// Level filter with per-target directives
+///
+/// Directives are written like `env_logger` ones: a comma separated list
+/// of `target=level` pairs, with a bare `level` setting the default level
+/// and a bare `target` enabling all records for that target:
+///
+/// ```rust
+/// extern crate log;
+/// extern crate kernlog;
+///
+/// use log::LevelFilter;
+/// use kernlog::Filter;
+///
+/// fn main() {
+///     let filter: Filter = "mycrate=debug,hyper=warn,info".parse().unwrap();
+///     assert_eq!(filter.level_for("mycrate::db"), LevelFilter::Debug);
+///     assert_eq!(filter.level_for("hyper::client"), LevelFilter::Warn);
+///     assert_eq!(filter.level_for("mio"), LevelFilter::Info);
+///     assert_eq!(filter.max_level(), LevelFilter::Debug);
+/// }
+/// ```
+///
+/// The directive with the longest target prefix matching a record's target
+/// is used, records matching no directive use the default level (`Info`
+/// unless set explicitly).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    level: LevelFilter,
+    directives: Vec<(String, LevelFilter)>,
+}
+
+impl Filter {
+    /// Create new filter with default level and no directives
+    pub fn new(level: LevelFilter) -> Filter {
+        Filter {
+            level,
+            directives: Vec::new(),
+        }
+    }
+
+    /// Default level for records matching no directive
+    pub fn level(&self) -> LevelFilter {
+        self.level
+    }
+
+    /// Set default level for records matching no directive
+    pub fn set_level(&mut self, level: LevelFilter) {
+        self.level = level;
+    }
+
+    /// Add directive for records with targets starting with `target`,
+    /// replacing previous directive for the same target
+    pub fn add_directive<S: Into<String>>(&mut self, target: S, level: LevelFilter) {
+        let target = target.into();
+        self.directives.retain(|(t, _)| *t != target);
+        let pos = self.directives.iter()
+            .position(|(t, _)| t.len() < target.len())
+            .unwrap_or(self.directives.len());
+        self.directives.insert(pos, (target, level));
+    }
+
+    /// Level filter used for records with given target
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.directives.iter()
+            .find(|(prefix, _)| target.starts_with(prefix.as_str()))
+            .map_or(self.level, |&(_, level)| level)
+    }
+
+    /// Most verbose level filter any target may use
+    pub fn max_level(&self) -> LevelFilter {
+        self.directives.iter().fold(self.level, |max, &(_, level)| cmp::max(max, level))
+    }
+
+    /// Check if a record with given metadata passes the filter
+    pub fn enabled(&self, meta: &Metadata) -> bool {
+        meta.level() <= self.level_for(meta.target())
+    }
+
+    /// Apply directives from `spec` on top of this filter, skipping
+    /// invalid directives and returning their errors
+    pub(crate) fn apply(&mut self, spec: &str) -> Vec<ParseFilterError> {
+        let mut errors = Vec::new();
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let mut parts = directive.splitn(2, '=');
+            let name = parts.next().unwrap_or("").trim();
+            match parts.next().map(str::trim) {
+                Some(level) => match level.parse() {
+                    Ok(level) if !name.is_empty() => self.add_directive(name, level),
+                    _ => errors.push(ParseFilterError(directive.to_owned())),
+                },
+                None => match name.parse() {
+                    Ok(level) => self.set_level(level),
+                    Err(_) => self.add_directive(name, LevelFilter::Trace),
+                },
+            }
+        }
+
+        errors
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Filter {
+        Filter::new(LevelFilter::Info)
+    }
+}
+
+impl FromStr for Filter {
+    type Err = ParseFilterError;
+
+    fn from_str(spec: &str) -> Result<Filter, ParseFilterError> {
+        let mut filter = Filter::default();
+        match filter.apply(spec).into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(filter),
+        }
+    }
+}
+
+/// Error returned when a filter directive can't be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseFilterError(pub(crate) String);
+
+impl fmt::Display for ParseFilterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid filter directive or level: {:?}", self.0)
+    }
+}
+
+impl error::Error for ParseFilterError {}
+
+#[cfg(test)]
+mod tests {
+    use log::LevelFilter;
+
+    use super::Filter;
+
+    #[test]
+    fn parse_directives() {
+        let filter: Filter = "info, mycrate=debug,mycrate::db=off,hyper=WARN,tokio".parse().unwrap();
+        assert_eq!(filter.level(), LevelFilter::Info);
+        assert_eq!(filter.level_for("mycrate"), LevelFilter::Debug);
+        assert_eq!(filter.level_for("mycrate::db::pool"), LevelFilter::Off);
+        assert_eq!(filter.level_for("hyper"), LevelFilter::Warn);
+        assert_eq!(filter.level_for("tokio::io"), LevelFilter::Trace);
+        assert_eq!(filter.level_for("mio"), LevelFilter::Info);
+        assert_eq!(filter.max_level(), LevelFilter::Trace);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!("mycrate=loud".parse::<Filter>().is_err());
+        assert!("=debug".parse::<Filter>().is_err());
+        assert_eq!("".parse::<Filter>(), Ok(Filter::default()));
+    }
+}
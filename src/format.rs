@@ -0,0 +1,127 @@
+use std::io::{self, Write};
+
+use log::Record;
+
+/// Formatter of log record message bodies
+///
+/// Formatter writes everything after the `<priority>ident[pid]: ` header,
+/// without trailing newline. Closures with matching signature are
+/// formatters too:
+///
+/// ```rust,no_run
+/// extern crate log;
+/// extern crate kernlog;
+///
+/// use std::io::Write;
+/// use kernlog::KernelLog;
+///
+/// fn main() {
+///     let logger = KernelLog::builder()
+///         .formatter(|buf: &mut Vec<u8>, record: &log::Record| {
+///             write!(buf, "[{}] {}", record.target(), record.args())
+///         })
+///         .build()
+///         .unwrap();
+/// }
+/// ```
+pub trait Formatter: Send + Sync {
+    /// Write message body for `record` into `buf`
+    fn format(&self, buf: &mut Vec<u8>, record: &Record) -> io::Result<()>;
+}
+
+impl<F> Formatter for F where F: Fn(&mut Vec<u8>, &Record) -> io::Result<()> + Send + Sync {
+    fn format(&self, buf: &mut Vec<u8>, record: &Record) -> io::Result<()> {
+        self(buf, record)
+    }
+}
+
+/// Message prefixed with record target: `mycrate::db: message` (default)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TargetFormatter;
+
+impl Formatter for TargetFormatter {
+    fn format(&self, buf: &mut Vec<u8>, record: &Record) -> io::Result<()> {
+        write!(buf, "{}: {}", record.target(), record.args())
+    }
+}
+
+/// Plain message without any prefix: `message`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessageFormatter;
+
+impl Formatter for MessageFormatter {
+    fn format(&self, buf: &mut Vec<u8>, record: &Record) -> io::Result<()> {
+        write!(buf, "{}", record.args())
+    }
+}
+
+/// Message prefixed with module path and source location:
+/// `mycrate::db (src/db.rs:42): message`
+///
+/// Parts unknown for a record are skipped, falling back to the target
+/// for a missing module path.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SourceFormatter;
+
+impl Formatter for SourceFormatter {
+    fn format(&self, buf: &mut Vec<u8>, record: &Record) -> io::Result<()> {
+        buf.extend_from_slice(record.module_path().unwrap_or_else(|| record.target()).as_bytes());
+        match (record.file(), record.line()) {
+            (Some(file), Some(line)) => write!(buf, " ({}:{})", file, line)?,
+            (Some(file), None) => write!(buf, " ({})", file)?,
+            _ => (),
+        }
+        write!(buf, ": {}", record.args())
+    }
+}
+
+/// Message prefixed with the last segment of record target only:
+/// `db: message`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompactFormatter;
+
+impl Formatter for CompactFormatter {
+    fn format(&self, buf: &mut Vec<u8>, record: &Record) -> io::Result<()> {
+        let target = record.target();
+        let name = target.rsplit("::").next().unwrap_or(target);
+        write!(buf, "{}: {}", name, record.args())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::{Level, Record};
+
+    use super::{CompactFormatter, Formatter, MessageFormatter, SourceFormatter, TargetFormatter};
+
+    fn format<F: Formatter>(formatter: F, record: &Record) -> String {
+        let mut buf = Vec::new();
+        formatter.format(&mut buf, record).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn builtin_formatters() {
+        let args = format_args!("hello");
+        let record = Record::builder()
+            .level(Level::Info)
+            .target("app::db")
+            .module_path(Some("app::db::pool"))
+            .file(Some("src/db/pool.rs"))
+            .line(Some(42))
+            .args(args)
+            .build();
+
+        assert_eq!(format(TargetFormatter, &record), "app::db: hello");
+        assert_eq!(format(MessageFormatter, &record), "hello");
+        assert_eq!(format(SourceFormatter, &record), "app::db::pool (src/db/pool.rs:42): hello");
+        assert_eq!(format(CompactFormatter, &record), "db: hello");
+    }
+
+    #[test]
+    fn source_without_location() {
+        let args = format_args!("hello");
+        let record = Record::builder().target("app").args(args).build();
+        assert_eq!(format(SourceFormatter, &record), "app: hello");
+    }
+}
@@ -0,0 +1,208 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::mem;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::os::unix::net::UnixDatagram;
+use std::path::Path;
+use std::process;
+use std::ptr;
+
+use libc;
+use log::Record;
+
+use Priority;
+
+/// Maximum length of journal field names
+const FIELD_NAME_MAX: usize = 64;
+
+/// Journald native protocol socket writer, sending each write
+/// as a single journal entry
+///
+/// Entries too large for a datagram are written into a sealed memfd,
+/// which is passed to journald instead.
+#[derive(Debug)]
+pub(crate) struct Journal(UnixDatagram);
+
+impl Journal {
+    pub(crate) fn connect<P: AsRef<Path>>(path: P) -> io::Result<Journal> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(path)?;
+        Ok(Journal(socket))
+    }
+
+    fn send_memfd(&self, entry: &[u8]) -> io::Result<()> {
+        let name = b"kernlog-journal\0".as_ptr() as *const libc::c_char;
+        let fd = unsafe { libc::memfd_create(name, libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut file = unsafe { File::from_raw_fd(fd) };
+        file.write_all(entry)?;
+
+        let seals = libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_WRITE | libc::F_SEAL_SEAL;
+        if unsafe { libc::fcntl(fd, libc::F_ADD_SEALS, seals) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // control message buffer, aligned for cmsghdr
+        let mut control = [0u64; 4];
+        unsafe {
+            let space = libc::CMSG_SPACE(mem::size_of::<libc::c_int>() as u32) as usize;
+            debug_assert!(space <= mem::size_of_val(&control));
+
+            let mut msg: libc::msghdr = mem::zeroed();
+            msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+            msg.msg_controllen = space as _;
+
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<libc::c_int>() as u32) as _;
+            ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut libc::c_int, fd);
+
+            if libc::sendmsg(self.0.as_raw_fd(), &msg, libc::MSG_NOSIGNAL) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Write for Journal {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.0.send(buf) {
+            Ok(_) => (),
+            Err(ref err) if matches!(err.raw_os_error(), Some(libc::EMSGSIZE) | Some(libc::ENOBUFS)) => {
+                self.send_memfd(buf)?;
+            }
+            Err(err) => return Err(err),
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Encode journal entry with message, priority and identifier fields
+pub(crate) fn entry(priority: Priority, ident: Option<&str>, pid: bool, message: &[u8]) -> Vec<u8> {
+    let mut entry = Vec::new();
+    field(&mut entry, "PRIORITY", priority.severity.code().to_string().as_bytes());
+    field(&mut entry, "SYSLOG_FACILITY", priority.facility.code().to_string().as_bytes());
+    if let Some(ident) = ident {
+        field(&mut entry, "SYSLOG_IDENTIFIER", ident.as_bytes());
+        if pid {
+            field(&mut entry, "SYSLOG_PID", process::id().to_string().as_bytes());
+        }
+    }
+    field(&mut entry, "MESSAGE", message.strip_suffix(b"\n").unwrap_or(message));
+    entry
+}
+
+/// Append source code location fields of `record`
+pub(crate) fn record_fields(entry: &mut Vec<u8>, record: &Record) {
+    if let Some(file) = record.file() {
+        field(entry, "CODE_FILE", file.as_bytes());
+    }
+    if let Some(line) = record.line() {
+        field(entry, "CODE_LINE", line.to_string().as_bytes());
+    }
+    if let Some(module) = record.module_path() {
+        field(entry, "CODE_MODULE", module.as_bytes());
+    }
+}
+
+/// Append `NAME=value` field, or `NAME\n<64-bit length>value` one
+/// if the value spans multiple lines
+pub(crate) fn field(entry: &mut Vec<u8>, name: &str, value: &[u8]) {
+    entry.extend_from_slice(name.as_bytes());
+    if value.contains(&b'\n') {
+        entry.push(b'\n');
+        entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        entry.push(b'=');
+    }
+    entry.extend_from_slice(value);
+    entry.push(b'\n');
+}
+
+/// Convert key into valid journal field name: uppercase letters, digits
+/// and underscores, starting with a letter
+#[cfg_attr(not(feature = "kv"), allow(dead_code))]
+pub(crate) fn field_name(key: &str) -> Option<String> {
+    let name: String = key.chars()
+        .skip_while(|c| !c.is_ascii_alphabetic())
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .take(FIELD_NAME_MAX)
+        .collect();
+    if name.is_empty() { None } else { Some(name) }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::{Read, Seek, SeekFrom, Write};
+    use std::mem;
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+    use std::os::unix::net::UnixDatagram;
+    use std::ptr;
+
+    use libc;
+    use log::{Level, Record};
+
+    use super::{entry, field_name, record_fields, Journal};
+    use {Facility, Priority, Severity};
+
+    #[test]
+    fn entries() {
+        let priority = Priority::new(Facility::Daemon, Severity::Error);
+        assert_eq!(entry(priority, Some("hook"), false, b"failed\n"),
+                   b"PRIORITY=3\nSYSLOG_FACILITY=3\nSYSLOG_IDENTIFIER=hook\nMESSAGE=failed\n");
+        assert_eq!(entry(priority, None, true, b"two\nlines"),
+                   b"PRIORITY=3\nSYSLOG_FACILITY=3\nMESSAGE\n\x09\0\0\0\0\0\0\0two\nlines\n");
+
+        let record = Record::builder().level(Level::Info).file(Some("src/main.rs")).line(Some(42)).build();
+        let mut fields = Vec::new();
+        record_fields(&mut fields, &record);
+        assert_eq!(fields, b"CODE_FILE=src/main.rs\nCODE_LINE=42\n");
+    }
+
+    #[test]
+    fn field_names() {
+        assert_eq!(field_name("user_id").as_deref(), Some("USER_ID"));
+        assert_eq!(field_name("_1http.status").as_deref(), Some("HTTP_STATUS"));
+        assert_eq!(field_name("42"), None);
+        assert_eq!(field_name(&"k".repeat(100)).map(|name| name.len()), Some(64));
+    }
+
+    #[test]
+    fn large_entries() {
+        let (tx, rx) = UnixDatagram::pair().unwrap();
+        let size: libc::c_int = 4096;
+        unsafe {
+            libc::setsockopt(tx.as_raw_fd(), libc::SOL_SOCKET, libc::SO_SNDBUF,
+                             &size as *const _ as *const libc::c_void, mem::size_of_val(&size) as libc::socklen_t);
+        }
+        let mut journal = Journal(tx);
+        let large = entry(Priority::new(Facility::User, Severity::Info), None, false, &[b'x'; 100_000]);
+        journal.write_all(&large).unwrap();
+
+        // empty datagram carrying memfd with the entry
+        let fd = unsafe {
+            let mut control = [0u64; 4];
+            let mut msg: libc::msghdr = mem::zeroed();
+            msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+            msg.msg_controllen = mem::size_of_val(&control) as _;
+            assert_eq!(libc::recvmsg(rx.as_raw_fd(), &mut msg, 0), 0);
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            assert_eq!((*cmsg).cmsg_type, libc::SCM_RIGHTS);
+            ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::c_int)
+        };
+        let mut file = unsafe { File::from_raw_fd(fd) };
+        let mut contents = Vec::new();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, large);
+    }
+}
@@ -0,0 +1,168 @@
+use std::io::Write;
+
+use log::kv::{self, Key, Value, VisitSource};
+use log::Record;
+
+use journald;
+use {Facility, Severity};
+
+/// Record key promoted into message header as its severity
+pub const PRIORITY_KEY: &str = "priority";
+
+/// Record key promoted into message header as its facility
+pub const FACILITY_KEY: &str = "facility";
+
+/// Selection of record key-values appended to messages
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum KvFilter {
+    /// Append all key-values (default)
+    #[default]
+    All,
+    /// Don't append any key-values
+    Off,
+    /// Append only listed keys
+    Only(Vec<String>),
+    /// Append all keys except listed ones
+    Except(Vec<String>),
+}
+
+impl KvFilter {
+    fn includes(&self, key: &str) -> bool {
+        match *self {
+            KvFilter::All => true,
+            KvFilter::Off => false,
+            KvFilter::Only(ref keys) => keys.iter().any(|k| k == key),
+            KvFilter::Except(ref keys) => !keys.iter().any(|k| k == key),
+        }
+    }
+}
+
+/// Collect severity and facility promoted from record key-values
+pub(crate) fn promoted(record: &Record) -> (Option<Severity>, Option<Facility>) {
+    struct Visitor(Option<Severity>, Option<Facility>);
+
+    impl<'kvs> VisitSource<'kvs> for Visitor {
+        fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+            match key.as_str() {
+                PRIORITY_KEY => self.0 = value.to_string().parse().ok(),
+                FACILITY_KEY => self.1 = value.to_string().parse().ok(),
+                _ => (),
+            }
+            Ok(())
+        }
+    }
+
+    let mut visitor = Visitor(None, None);
+    let _ = record.key_values().visit(&mut visitor);
+    (visitor.0, visitor.1)
+}
+
+/// Append record key-values selected by `filter` to `buf`
+/// in logfmt style: ` key=value key="quoted value"`
+pub(crate) fn append(buf: &mut Vec<u8>, record: &Record, filter: &KvFilter) {
+    struct Visitor<'a> {
+        buf: &'a mut Vec<u8>,
+        filter: &'a KvFilter,
+    }
+
+    impl<'a, 'kvs> VisitSource<'kvs> for Visitor<'a> {
+        fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+            let key = key.as_str();
+            if key == PRIORITY_KEY || key == FACILITY_KEY || !self.filter.includes(key) {
+                return Ok(());
+            }
+
+            let value = value.to_string();
+            write!(self.buf, " {}=", key)?;
+            if needs_quotes(&value) {
+                self.buf.push(b'"');
+                for c in value.chars() {
+                    if c == '"' || c == '\\' {
+                        self.buf.push(b'\\');
+                    }
+                    write!(self.buf, "{}", c)?;
+                }
+                self.buf.push(b'"');
+            } else {
+                self.buf.extend_from_slice(value.as_bytes());
+            }
+            Ok(())
+        }
+    }
+
+    if *filter != KvFilter::Off {
+        let _ = record.key_values().visit(&mut Visitor { buf, filter });
+    }
+}
+
+/// Append record key-values selected by `filter` to journal `entry`
+/// as fields with uppercased names
+pub(crate) fn journal_fields(entry: &mut Vec<u8>, record: &Record, filter: &KvFilter) {
+    struct Visitor<'a> {
+        entry: &'a mut Vec<u8>,
+        filter: &'a KvFilter,
+    }
+
+    impl<'a, 'kvs> VisitSource<'kvs> for Visitor<'a> {
+        fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+            let key = key.as_str();
+            if key == PRIORITY_KEY || key == FACILITY_KEY || !self.filter.includes(key) {
+                return Ok(());
+            }
+            if let Some(name) = journald::field_name(key) {
+                journald::field(self.entry, &name, value.to_string().as_bytes());
+            }
+            Ok(())
+        }
+    }
+
+    if *filter != KvFilter::Off {
+        let _ = record.key_values().visit(&mut Visitor { entry, filter });
+    }
+}
+
+fn needs_quotes(value: &str) -> bool {
+    value.is_empty() || value.chars().any(|c| c <= ' ' || c == '=' || c == '"' || c == '\\')
+}
+
+#[cfg(test)]
+mod tests {
+    use log::{Level, Record};
+
+    use super::{append, journal_fields, promoted, KvFilter};
+    use {Facility, Severity};
+
+    fn format(record: &Record, filter: &KvFilter) -> String {
+        let mut buf = Vec::new();
+        append(&mut buf, record, filter);
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn logfmt() {
+        let kvs = [("user", "root"), ("path", "/var/lib/my app"), ("quote", "say \"hi\""), ("empty", "")];
+        let record = Record::builder().level(Level::Info).key_values(&kvs).build();
+        assert_eq!(format(&record, &KvFilter::All), r#" user=root path="/var/lib/my app" quote="say \"hi\"" empty="""#);
+        assert_eq!(format(&record, &KvFilter::Off), "");
+        assert_eq!(format(&record, &KvFilter::Only(vec!["user".to_owned()])), " user=root");
+        assert_eq!(format(&record, &KvFilter::Except(vec!["user".to_owned(), "empty".to_owned()])),
+            r#" path="/var/lib/my app" quote="say \"hi\"""#);
+    }
+
+    #[test]
+    fn promote_header_keys() {
+        let kvs = [("priority", "crit"), ("facility", "daemon"), ("user", "root")];
+        let record = Record::builder().level(Level::Error).key_values(&kvs).build();
+        assert_eq!(promoted(&record), (Some(Severity::Critical), Some(Facility::Daemon)));
+        assert_eq!(format(&record, &KvFilter::All), " user=root");
+    }
+
+    #[test]
+    fn journal_entry_fields() {
+        let kvs = [("priority", "crit"), ("user.id", "1000"), ("path", "/tmp")];
+        let record = Record::builder().level(Level::Error).key_values(&kvs).build();
+        let mut entry = Vec::new();
+        journal_fields(&mut entry, &record, &KvFilter::Except(vec!["path".to_owned()]));
+        assert_eq!(entry, b"USER_ID=1000\n");
+    }
+}
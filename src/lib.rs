@@ -26,76 +26,391 @@
 //! }
 //! ```
 //! Note you have to have permissions to write to `/dev/kmsg`,
-//! which normal users (not root) usually don't.
+//! which normal users (not root) usually don't. If the device is missing
+//! or not writable, `init()` returns an [`Error`](enum.Error.html)
+//! instead of panicking. To keep logging in containers and unprivileged
+//! runs, let the builder fall back to other [`Backend`](enum.Backend.html)s:
+//! syslog, journald and stderr, with `backends(Backend::ALL.iter().cloned())`.
+//! Syslog messages are framed as RFC 3164 or RFC 5424, see
+//! [`SyslogFormat`](enum.SyslogFormat.html).
+//!
+//! Use [`KernelLog::builder()`](struct.KernelLog.html#method.builder) to
+//! configure the logger before installing it.
+//!
+//! Tools that can't take command line flags, like early boot helpers,
+//! may use [`init_from_env()`](fn.init_from_env.html) instead: it reads
+//! filter directives (e.g. `mycrate=debug,hyper=warn,info`) from
+//! the `KERNLOG` environment variable and the `kernlog=` kernel
+//! command line parameter, honoring systemd's `systemd.log_level=`
+//! and `debug` parameters as well.
+//!
+//! Records are emitted with the conventional syslog severities (`Error`
+//! as `err`, `Warn` as `warning`, `Info` as `info`, `Debug` and `Trace`
+//! as `debug`), which can be remapped with `KernelLogBuilder::severity()`,
+//! and marked with `user` facility, which can be changed with
+//! `KernelLogBuilder::facility()` to tell them apart from other messages.
+//! Fatal conditions can be reported with `emerg!`, `alert!` and `crit!`
+//! macros, which log at `Error` level with raised severity:
+//!
+//! ```rust,no_run
+//! #[macro_use]
+//! extern crate kernlog;
+//!
+//! fn main() {
+//!     kernlog::init().unwrap();
+//!     crit!("root filesystem {} not found", "/dev/sda1");
+//!     emerg!(target: "boot", "giving up");
+//! }
+//! ```
 //! 
-//! If compiled with nightly it can use libc feature to get process id
-//! and report it into log. This feature is unavailable for stable release
-//! for now. To enable nightly features, compile with `--features nightly`:
+//! Messages are prefixed with the program identifier (executable name
+//! by default) and the process id in the usual syslog `ident[pid]: ` form,
+//! so `dmesg` shows which program logged a line:
 //!
-//! ```toml
-//! [dependencies.kernlog]
-//! version = "*"
-//! features = ["nightly"]
+//! ```text
+//! [    3.141592] my-generator[271]: my_generator: something strange happened
 //! ```
+//!
+//! The rest of the line is written by a [`Formatter`](trait.Formatter.html),
+//! which prefixes messages with record target by default. Other formatters
+//! can be set with `KernelLogBuilder::formatter()`.
+//!
+//! With `kv` feature enabled, record key-values are appended to messages
+//! in logfmt style (`key=value key="quoted value"`), except `priority`
+//! and `facility` keys, which override record severity and facility.
+//!
+//! When messages don't show up, [`probe()`](fn.probe.html) reports
+//! whether `/dev/kmsg` is accessible and how the kernel is configured
+//! to treat userspace messages.
+//!
+//! The kernel ring buffer can be read back with
+//! [`KmsgReader`](struct.KmsgReader.html), which parses `/dev/kmsg`
+//! records into [`KmsgRecord`](struct.KmsgRecord.html)s, whose timestamps
+//! can be converted to wall-clock time with [`BootClock`](struct.BootClock.html).
+//! With `tokio` feature enabled, [`AsyncKmsgReader`](struct.AsyncKmsgReader.html)
+//! yields them as a `Stream` driven by the tokio reactor.
 
 #![deny(missing_docs)]
 
-#[macro_use]
+#[cfg_attr(test, macro_use)]
 extern crate log;
+extern crate libc;
+#[cfg(feature = "tokio")]
+extern crate futures_core;
+#[cfg(feature = "tokio")]
+extern crate tokio;
 
-use std::fs::{OpenOptions, File};
-use std::io::Write;
+use std::cmp;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
 use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Instant, SystemTime};
+
+use log::{Log, Metadata, Record, Level, LevelFilter};
+
+use ratelimit::RateLimiter;
+
+#[doc(hidden)]
+pub use log::log as __log;
+#[doc(hidden)]
+pub use log::Level as __Level;
 
-use log::{Log, Metadata, Record, Level, LevelFilter, SetLoggerError};
+#[cfg(feature = "tokio")]
+mod async_reader;
+mod backend;
+mod builder;
+mod chunk;
+mod clock;
+mod cursor;
+mod env;
+mod error;
+mod filter;
+mod format;
+mod journald;
+#[cfg(feature = "kv")]
+mod kv;
+mod priority;
+mod probe;
+mod ratelimit;
+mod reader;
+mod sanitize;
+mod syslog;
+
+#[cfg(feature = "tokio")]
+pub use async_reader::{AsyncEvents, AsyncKmsgReader};
+pub use backend::{Backend, JOURNALD_SOCKET, SYSLOG_SOCKET};
+pub use builder::{KernelLogBuilder, DEFAULT_DEVICE};
+pub use chunk::{WritePolicy, DEFAULT_RECORD_MAX, MIN_RECORD_MAX};
+pub use clock::{BootClock, DEFAULT_REFRESH_INTERVAL};
+pub use cursor::{boot_id, Cursor, ParseCursorError, Resume, BOOT_ID_PATH};
+pub use env::{ENV_VAR, CMDLINE_PARAM, CMDLINE_PATH};
+pub use error::Error;
+pub use filter::{Filter, ParseFilterError};
+pub use format::{Formatter, TargetFormatter, MessageFormatter, SourceFormatter, CompactFormatter};
+#[cfg(feature = "kv")]
+pub use kv::{KvFilter, PRIORITY_KEY, FACILITY_KEY};
+pub use priority::{with_severity, Facility, ParsePriorityError, Priority, Severity, SeverityMap};
+pub use probe::{probe, probe_device, ConsoleLevels, DevkmsgMode, ParseSettingError, Probe};
+pub use probe::{DMESG_RESTRICT_PATH, PRINTK_DEVKMSG_PATH, PRINTK_PATH};
+pub use ratelimit::RateLimit;
+pub use reader::{Caller, Continuation, Event, Events, Flags, KmsgReader, KmsgRecord, ParseRecordError, Position};
+pub use sanitize::Sanitize;
+pub use syslog::SyslogFormat;
 
 /// Kernel logger implementation
 pub struct KernelLog {
-    kmsg: Mutex<File>,
-    maxlevel: LevelFilter
+    kmsg: Mutex<Box<dyn Write + Send>>,
+    backend: Option<Backend>,
+    syslog_format: SyslogFormat,
+    filter: Filter,
+    severities: SeverityMap,
+    facility: Facility,
+    ident: Option<String>,
+    pid: bool,
+    formatter: Box<dyn Formatter>,
+    split_lines: bool,
+    continuation: Option<String>,
+    record_max: AtomicUsize,
+    policy: WritePolicy,
+    sanitize: Sanitize,
+    ratelimit: Option<Mutex<RateLimiter>>,
+    #[cfg(feature = "kv")]
+    kv: KvFilter,
 }
 
 impl KernelLog {
+    /// Create builder to configure new kernel logger
+    pub fn builder() -> KernelLogBuilder {
+        KernelLogBuilder::new()
+    }
+
     /// Create new kernel logger
+    ///
+    /// # Panics
+    ///
+    /// Panics if `/dev/kmsg` can't be opened for writing, see `try_new()`.
     pub fn new() -> KernelLog {
         KernelLog::with_level(LevelFilter::Info)
     }
 
     /// Create new kernel logger with error level filter
+    ///
+    /// # Panics
+    ///
+    /// Panics if `/dev/kmsg` can't be opened for writing, see `try_with_level()`.
     pub fn with_level(filter: LevelFilter) -> KernelLog {
-        KernelLog {
-            kmsg: Mutex::new(OpenOptions::new().write(true).open("/dev/kmsg").unwrap()),
-            maxlevel: filter
+        KernelLog::try_with_level(filter).unwrap()
+    }
+
+    /// Create new kernel logger, failing if `/dev/kmsg` can't be opened
+    pub fn try_new() -> io::Result<KernelLog> {
+        KernelLog::try_with_level(LevelFilter::Info)
+    }
+
+    /// Create new kernel logger with error level filter,
+    /// failing if `/dev/kmsg` can't be opened
+    pub fn try_with_level(filter: LevelFilter) -> io::Result<KernelLog> {
+        KernelLog::builder().level(filter).build()
+    }
+
+    /// Create new kernel logger writing to device or file at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<KernelLog> {
+        KernelLog::builder().device(path).build()
+    }
+
+    /// Create new kernel logger writing to already opened file
+    pub fn from_file(file: File) -> KernelLog {
+        KernelLog::from_writer(file)
+    }
+
+    /// Create new kernel logger writing to arbitrary sink
+    pub fn from_writer<W: Write + Send + 'static>(writer: W) -> KernelLog {
+        KernelLog::builder().writer(writer).build().unwrap()
+    }
+
+    /// Level filter used by this logger
+    pub fn filter(&self) -> &Filter {
+        &self.filter
+    }
+
+    /// Backend selected when the logger was built,
+    /// `None` if it writes to a custom writer
+    pub fn backend(&self) -> Option<Backend> {
+        self.backend
+    }
+
+    /// Set up this logger as a default logger
+    fn install(self) -> Result<(), Error> {
+        let max_level = self.filter().max_level();
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+}
+
+impl KernelLog {
+    /// Write preformatted message with given priority, bypassing
+    /// level filter and formatter
+    ///
+    /// The message is prefixed with the identifier, split into lines,
+    /// chunked and sanitized the same way as logged records. Syslog
+    /// messages get a timestamped header in the configured format,
+    /// journal entries keep the message whole and unescaped.
+    pub fn write_message(&self, priority: Priority, message: &[u8]) -> io::Result<()> {
+        let header = if self.backend == Some(Backend::Syslog) {
+            syslog::header(self.syslog_format, priority, self.ident.as_deref(), self.pid, SystemTime::now())
+        } else {
+            let mut header = Vec::new();
+            write!(header, "<{}>", priority.code())?;
+            if let Some(ref ident) = self.ident {
+                header.extend_from_slice(ident.as_bytes());
+                if self.pid {
+                    write!(header, "[{}]", process::id())?;
+                }
+                header.extend_from_slice(b": ");
+            }
+            header
+        };
+
+        let mut kmsg = match self.kmsg.lock() {
+            Ok(kmsg) => kmsg,
+            Err(_) => return Err(io::Error::other("kmsg writer lock poisoned")),
+        };
+        if self.backend == Some(Backend::Journald) {
+            let entry = journald::entry(priority, self.ident.as_deref(), self.pid, message);
+            kmsg.write_all(&entry)?;
+        } else if self.split_lines {
+            let message = message.strip_suffix(b"\n").unwrap_or(message);
+            for (n, line) in message.split(|&b| b == b'\n').enumerate() {
+                let line = line.strip_suffix(b"\r").unwrap_or(line);
+                let marker = match self.continuation {
+                    Some(ref marker) if n > 0 => marker.as_bytes(),
+                    _ => b"",
+                };
+                let line = sanitize::sanitize(line, self.sanitize);
+                self.write_line(&mut **kmsg, &header, &[marker, &line])?;
+            }
+        } else {
+            let message = sanitize::sanitize(message, self.sanitize);
+            self.write_line(&mut **kmsg, &header, &[&message])?;
+        }
+        kmsg.flush()
+    }
+
+    /// Current record length limit, lowered automatically
+    /// if the kernel refuses records of configured length
+    pub fn record_max(&self) -> usize {
+        self.record_max.load(Ordering::Relaxed)
+    }
+
+    /// Check record from `target` against rate limits, writing summaries
+    /// of suppressed records if logging resumes
+    fn rate_limited(&self, target: &str) -> bool {
+        let limiter = match self.ratelimit {
+            Some(ref limiter) => limiter,
+            None => return false,
+        };
+        // a panic while holding the lock can't leave the buckets
+        // inconsistent, keep using them rather than dropping all records
+        let summaries = match limiter.lock() {
+            Ok(mut limiter) => limiter.check(target, Instant::now()),
+            Err(poisoned) => poisoned.into_inner().check(target, Instant::now()),
+        };
+        match summaries {
+            Some(summaries) => {
+                for (target, count) in summaries {
+                    let message = format!("{} messages suppressed from {}", count, target);
+                    let _ = self.write_message(Priority::new(self.facility, Severity::Warning), message.as_bytes());
+                }
+                false
+            }
+            None => true,
+        }
+    }
+
+    fn write_line(&self, kmsg: &mut dyn Write, header: &[u8], parts: &[&[u8]]) -> io::Result<()> {
+        let line = parts.concat();
+
+        'retry: loop {
+            let limit = self.record_max();
+            let chunks = chunk::split(&line, header.len(), limit, self.policy);
+            let total = chunks.len();
+
+            for (n, chunk) in chunks.into_iter().enumerate() {
+                let mut buf = header.to_vec();
+                if total > 1 {
+                    write!(buf, "[{}/{}] ", n + 1, total).unwrap();
+                }
+                buf.extend_from_slice(chunk);
+                buf.push(b'\n');
+
+                match kmsg.write_all(&buf) {
+                    Ok(()) => (),
+                    // later fragments are never longer than the first one,
+                    // so only the first one may exceed the kernel limit
+                    Err(ref err) if err.kind() == io::ErrorKind::InvalidInput && n == 0 && limit > MIN_RECORD_MAX => {
+                        let limit = cmp::max(cmp::min(limit, buf.len()) - 1, MIN_RECORD_MAX);
+                        self.record_max.store(limit, Ordering::Relaxed);
+                        continue 'retry;
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+
+            return Ok(());
         }
     }
+}
 
+impl Default for KernelLog {
+    fn default() -> KernelLog {
+        KernelLog::new()
+    }
 }
 
 impl Log for KernelLog {
     fn enabled(&self, meta: &Metadata) -> bool {
-        meta.level() <= self.maxlevel
+        self.filter.enabled(meta)
     }
 
     fn log(&self, record: &Record) {
-        if !self.enabled(record.metadata()) {
+        if !self.enabled(record.metadata()) || self.rate_limited(record.target()) {
             return;
         }
 
-        let level: u8 = match record.level() {
-            Level::Error => 3,
-            Level::Warn => 4,
-            Level::Info => 5,
-            Level::Debug => 6,
-            Level::Trace => 7,
-        };
+        #[cfg(feature = "kv")]
+        let (severity, facility) = kv::promoted(record);
+        #[cfg(not(feature = "kv"))]
+        let (severity, facility) = (None, None);
 
-        let mut buf = Vec::new();
-        writeln!(buf, "<{}>{}: {}", level, record.target(), record.args()).unwrap();
+        let severity = priority::severity_override()
+            .or(severity)
+            .unwrap_or_else(|| self.severities.get(record.level()));
+        let priority = Priority::new(facility.unwrap_or(self.facility), severity);
 
-        if let Ok(mut kmsg) = self.kmsg.lock() {
-            let _ = kmsg.write(&buf);
-            let _ = kmsg.flush();
+        let mut body = Vec::new();
+        if self.formatter.format(&mut body, record).is_err() {
+            return;
         }
+
+        if self.backend == Some(Backend::Journald) {
+            let mut entry = journald::entry(priority, self.ident.as_deref(), self.pid, &body);
+            journald::record_fields(&mut entry, record);
+            #[cfg(feature = "kv")]
+            kv::journal_fields(&mut entry, record, &self.kv);
+            if let Ok(mut kmsg) = self.kmsg.lock() {
+                let _ = kmsg.write_all(&entry);
+            }
+            return;
+        }
+
+        #[cfg(feature = "kv")]
+        kv::append(&mut body, record, &self.kv);
+
+        let _ = self.write_message(priority, &body);
     }
 
     fn flush(&self) {
@@ -106,21 +421,356 @@ impl Log for KernelLog {
 }
 
 /// Setup kernel logger as a default logger
-pub fn init() -> Result<(), SetLoggerError> {
+pub fn init() -> Result<(), Error> {
     init_with_level(Level::Trace)
 }
 
 /// init KernLog with level
-pub fn init_with_level(level: Level) -> Result<(), SetLoggerError> {
-    let logger = KernelLog::with_level(level.to_level_filter());
-    log::set_boxed_logger(Box::new(logger))?;
-    log::set_max_level(level.to_level_filter());
-    Ok(())
+pub fn init_with_level(level: Level) -> Result<(), Error> {
+    KernelLog::builder().level(level.to_level_filter()).init()
+}
+
+/// init KernLog with filter read from the kernel command line
+/// and environment, see `Filter::from_env()`
+///
+/// Invalid levels and directives are skipped and reported
+/// as warnings through the installed logger.
+pub fn init_from_env() -> Result<(), Error> {
+    let (filter, errors) = Filter::from_env_with_errors();
+    let logger = KernelLog::builder().filter(filter).build()?;
+    for err in errors {
+        let priority = Priority::new(logger.facility, Severity::Warning);
+        let _ = logger.write_message(priority, format!("ignoring {}", err).as_bytes());
+    }
+    logger.install()
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{init};
+    use std::fs;
+    use std::io::{self, Write};
+    use std::process;
+    use std::os::unix::net::UnixDatagram;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    use log::{Level, LevelFilter, Log, Record};
+
+    use super::{init, with_severity, Backend, Facility, KernelLog, MessageFormatter, Priority, RateLimit, Sanitize, Severity};
+    use super::{SyslogFormat, WritePolicy};
+
+    #[derive(Clone, Default)]
+    pub struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Buffer {
+        pub fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn log(logger: &KernelLog, level: Level, target: &str, message: &str) {
+        logger.log(&Record::builder()
+            .level(level)
+            .target(target)
+            .args(format_args!("{}", message))
+            .build());
+    }
+
+    #[test]
+    fn log_to_writer() {
+        let buffer = Buffer::default();
+        let logger = KernelLog::builder().no_ident().writer(buffer.clone()).build().unwrap();
+        log(&logger, Level::Warn, "app", "warn.");
+        log(&logger, Level::Debug, "app", "debug.");
+        assert_eq!(buffer.contents(), "<12>app: warn.\n");
+    }
+
+    #[test]
+    fn severities() {
+        let buffer = Buffer::default();
+        let logger = KernelLog::builder()
+            .level(LevelFilter::Trace)
+            .severity(Level::Info, Severity::Notice)
+            .no_ident()
+            .writer(buffer.clone())
+            .build()
+            .unwrap();
+        log(&logger, Level::Info, "app", "info.");
+        log(&logger, Level::Trace, "app", "trace.");
+        with_severity(Severity::Critical, || log(&logger, Level::Error, "app", "fatal."));
+        assert_eq!(buffer.contents(), "<13>app: info.\n<15>app: trace.\n<10>app: fatal.\n");
+    }
+
+    #[test]
+    fn split_lines() {
+        let buffer = Buffer::default();
+        let logger = KernelLog::builder()
+            .ident("helper")
+            .pid(false)
+            .split_lines(true)
+            .continuation_marker("| ")
+            .writer(buffer.clone())
+            .build()
+            .unwrap();
+        log(&logger, Level::Error, "app", "failed\r\ncaused by: io\n");
+        log(&logger, Level::Error, "app", "single");
+        assert_eq!(buffer.contents(), "<11>helper: app: failed\n<11>helper: | caused by: io\n<11>helper: app: single\n");
+    }
+
+    #[test]
+    fn write_messages() {
+        let buffer = Buffer::default();
+        let logger = KernelLog::builder()
+            .level(LevelFilter::Error)
+            .ident("hook")
+            .pid(false)
+            .split_lines(true)
+            .writer(buffer.clone())
+            .build()
+            .unwrap();
+        logger.write_message(Priority::new(Facility::Daemon, Severity::Notice), b"mounted\nroot\x1b").unwrap();
+        assert_eq!(buffer.contents(), "<29>hook: mounted\n<29>hook: root\\x1b\n");
+    }
+
+    #[test]
+    fn rate_limits() {
+        let buffer = Buffer::default();
+        let logger = KernelLog::builder()
+            .no_ident()
+            .target_rate_limit(RateLimit::new(2, Duration::from_millis(200)))
+            .writer(buffer.clone())
+            .build()
+            .unwrap();
+        for _ in 0..5 {
+            log(&logger, Level::Info, "noisy", "spam");
+        }
+        log(&logger, Level::Info, "quiet", "hello");
+        thread::sleep(Duration::from_millis(150));
+        log(&logger, Level::Info, "noisy", "resumed");
+        assert_eq!(buffer.contents(), "<14>noisy: spam\n<14>noisy: spam\n<14>quiet: hello\n\
+                                       <12>3 messages suppressed from noisy\n<14>noisy: resumed\n");
+    }
+
+    struct Limited(Buffer, usize);
+
+    impl Write for Limited {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if buf.len() > self.1 {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "too long"));
+            }
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn oversized_records() {
+        let message = "0123456789".repeat(30);
+        let buffer = Buffer::default();
+        let logger = KernelLog::builder()
+            .ident("helper")
+            .pid(false)
+            .formatter(MessageFormatter)
+            .max_record_len(256)
+            .writer(Limited(buffer.clone(), 150))
+            .build()
+            .unwrap();
+        log(&logger, Level::Info, "app", &message);
+        assert_eq!(logger.record_max(), 150);
+
+        let contents = buffer.contents();
+        let lines = contents.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|line| line.len() < 150));
+        assert!(lines[0].starts_with("<14>helper: [1/3] 0123"));
+        assert!(lines[2].starts_with("<14>helper: [3/3] "));
+        let fragments = lines.iter().map(|line| line.split_once("] ").unwrap().1).collect::<String>();
+        assert_eq!(fragments, message);
+
+        // later records use the lowered limit right away
+        log(&logger, Level::Info, "app", &message[..130]);
+        assert!(buffer.contents().ends_with(&format!("<14>helper: {}\n", &message[..130])));
+    }
+
+    #[test]
+    fn truncated_records() {
+        let buffer = Buffer::default();
+        let logger = KernelLog::builder()
+            .ident("helper")
+            .pid(false)
+            .formatter(MessageFormatter)
+            .max_record_len(20)
+            .write_policy(WritePolicy::Truncate)
+            .writer(buffer.clone())
+            .build()
+            .unwrap();
+        log(&logger, Level::Info, "app", "0123456789");
+        assert_eq!(buffer.contents(), "<14>helper: 0123456\n");
+    }
+
+    #[test]
+    fn sanitized_records() {
+        let buffer = Buffer::default();
+        let logger = KernelLog::builder()
+            .no_ident()
+            .formatter(MessageFormatter)
+            .sanitize(Sanitize::Strict)
+            .writer(buffer.clone())
+            .build()
+            .unwrap();
+        log(&logger, Level::Info, "app", "<0>spoofed\n<0>panic\x1b[0m");
+        assert_eq!(buffer.contents(), "<14>\\x3c0>spoofed\\x0a<0>panic\\x1b[0m\n");
+    }
+
+    #[cfg(feature = "kv")]
+    #[test]
+    fn key_values() {
+        let buffer = Buffer::default();
+        let logger = KernelLog::builder().no_ident().writer(buffer.clone()).build().unwrap();
+        let kvs = [("priority", "alert"), ("disk", "sda")];
+        logger.log(&Record::builder()
+            .level(Level::Error)
+            .target("app")
+            .args(format_args!("disk failed"))
+            .key_values(&kvs)
+            .build());
+        assert_eq!(buffer.contents(), "<9>app: disk failed disk=sda\n");
+    }
+
+    #[test]
+    fn log_to_path() {
+        let path = ::std::env::temp_dir().join(format!("kernlog-test-{}", ::std::process::id()));
+        fs::File::create(&path).unwrap();
+        {
+            let logger = KernelLog::open(&path).unwrap();
+            log(&logger, Level::Error, "app", "error.");
+        }
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(contents.starts_with("<11>kernlog-"), "{}", contents);
+        assert!(contents.ends_with(&format!("[{}]: app: error.\n", process::id())), "{}", contents);
+    }
+
+    #[test]
+    fn log_to_journald() {
+        let path = ::std::env::temp_dir().join(format!("kernlog-journal-{}", process::id()));
+        let _ = fs::remove_file(&path);
+        let journal = UnixDatagram::bind(&path).unwrap();
+        let logger = KernelLog::builder()
+            .ident("hook")
+            .pid(false)
+            .backends(vec![Backend::Journald])
+            .journald_socket(&path)
+            .build()
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(logger.backend(), Some(Backend::Journald));
+
+        let record = Record::builder()
+            .args(format_args!("mounted"))
+            .level(Level::Warn)
+            .target("app")
+            .module_path(Some("app::mount"))
+            .file(Some("src/mount.rs"))
+            .line(Some(7))
+            .build();
+        logger.log(&record);
+        let mut buf = [0; 256];
+        let len = journal.recv(&mut buf).unwrap();
+        assert_eq!(String::from_utf8_lossy(&buf[..len]),
+                   "PRIORITY=4\nSYSLOG_FACILITY=1\nSYSLOG_IDENTIFIER=hook\nMESSAGE=app: mounted\n\
+                    CODE_FILE=src/mount.rs\nCODE_LINE=7\nCODE_MODULE=app::mount\n");
+    }
+
+    #[test]
+    fn log_to_syslog() {
+        let path = ::std::env::temp_dir().join(format!("kernlog-syslog-{}", process::id()));
+        let _ = fs::remove_file(&path);
+        let syslog = UnixDatagram::bind(&path).unwrap();
+        let logger = KernelLog::builder()
+            .ident("hook")
+            .facility(Facility::Daemon)
+            .backends(vec![Backend::Syslog])
+            .syslog_socket(&path)
+            .syslog_format(SyslogFormat::Rfc5424)
+            .build()
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(logger.backend(), Some(Backend::Syslog));
+
+        log(&logger, Level::Error, "app", "failed");
+        let mut buf = [0; 256];
+        let len = syslog.recv(&mut buf).unwrap();
+        let message = String::from_utf8_lossy(&buf[..len]);
+        assert!(message.starts_with("<27>1 "), "{}", message);
+        assert!(message.ends_with(&format!(" hook {} - - app: failed", process::id())), "{}", message);
+    }
+
+    #[test]
+    fn fallback_backends() {
+        let logger = KernelLog::builder()
+            .device("/nonexistent/kmsg")
+            .backends(vec![Backend::Kmsg, Backend::Stderr])
+            .build()
+            .unwrap();
+        assert_eq!(logger.backend(), Some(Backend::Stderr));
+
+        let result = KernelLog::builder().device("/nonexistent/kmsg").backends(Some(Backend::Kmsg)).build();
+        assert_eq!(result.err().map(|err| err.kind()), Some(io::ErrorKind::NotFound));
+        assert_eq!(KernelLog::builder().writer(Buffer::default()).build().unwrap().backend(), None);
+    }
+
+    #[test]
+    fn ident_and_pid() {
+        let buffer = Buffer::default();
+        let logger = KernelLog::builder()
+            .ident("helper")
+            .pid(false)
+            .formatter(MessageFormatter)
+            .writer(buffer.clone())
+            .build()
+            .unwrap();
+        log(&logger, Level::Info, "app", "info.");
+        assert_eq!(buffer.contents(), "<14>helper: info.\n");
+
+        let buffer = Buffer::default();
+        let logger = KernelLog::builder().ident("helper").writer(buffer.clone()).build().unwrap();
+        log(&logger, Level::Info, "app", "info.");
+        assert_eq!(buffer.contents(), format!("<14>helper[{}]: app: info.\n", process::id()));
+    }
+
+    #[test]
+    fn target_levels() {
+        let buffer = Buffer::default();
+        let logger = KernelLog::builder()
+            .level(LevelFilter::Warn)
+            .facility(Facility::Daemon)
+            .target_level("app", LevelFilter::Debug)
+            .target_level("app::noisy", LevelFilter::Error)
+            .no_ident()
+            .writer(buffer.clone())
+            .build()
+            .unwrap();
+        assert_eq!(logger.filter().max_level(), LevelFilter::Debug);
+        log(&logger, Level::Debug, "app::db", "query");
+        log(&logger, Level::Warn, "app::noisy", "noise");
+        log(&logger, Level::Info, "hyper", "request");
+        assert_eq!(buffer.contents(), "<31>app::db: query\n");
+    }
 
     #[test]
     fn log_to_kernel() {
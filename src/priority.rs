@@ -0,0 +1,485 @@
+use std::cell::Cell;
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use log::Level;
+
+/// Syslog message severity, as used by the kernel log levels
+/// (`KERN_EMERG` through `KERN_DEBUG`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// System is unusable
+    Emergency = 0,
+    /// Action must be taken immediately
+    Alert = 1,
+    /// Critical conditions
+    Critical = 2,
+    /// Error conditions
+    Error = 3,
+    /// Warning conditions
+    Warning = 4,
+    /// Normal but significant condition
+    Notice = 5,
+    /// Informational message
+    Info = 6,
+    /// Debug-level message
+    Debug = 7,
+}
+
+impl Severity {
+    /// All severities, from the most to the least severe
+    pub const ALL: [Severity; 8] = [
+        Severity::Emergency,
+        Severity::Alert,
+        Severity::Critical,
+        Severity::Error,
+        Severity::Warning,
+        Severity::Notice,
+        Severity::Info,
+        Severity::Debug,
+    ];
+
+    /// Get severity from its numeric code
+    pub fn from_code(code: u8) -> Option<Severity> {
+        Severity::ALL.get(code as usize).cloned()
+    }
+
+    /// Numeric severity code
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// Short syslog name of the severity (`emerg`, `err`, `info`, ...)
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Emergency => "emerg",
+            Severity::Alert => "alert",
+            Severity::Critical => "crit",
+            Severity::Error => "err",
+            Severity::Warning => "warning",
+            Severity::Notice => "notice",
+            Severity::Info => "info",
+            Severity::Debug => "debug",
+        }
+    }
+
+    /// Closest `log` level for the severity
+    pub fn to_level(self) -> Level {
+        match self {
+            Severity::Emergency | Severity::Alert | Severity::Critical | Severity::Error => Level::Error,
+            Severity::Warning => Level::Warn,
+            Severity::Notice | Severity::Info => Level::Info,
+            Severity::Debug => Level::Debug,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Severity {
+    type Err = ParsePriorityError;
+
+    /// Parse severity from its syslog name or numeric code
+    fn from_str(s: &str) -> Result<Severity, ParsePriorityError> {
+        let s = s.trim();
+        Ok(match &*s.to_ascii_lowercase() {
+            "emerg" | "panic" => Severity::Emergency,
+            "alert" => Severity::Alert,
+            "crit" => Severity::Critical,
+            "err" | "error" => Severity::Error,
+            "warning" | "warn" => Severity::Warning,
+            "notice" => Severity::Notice,
+            "info" => Severity::Info,
+            "debug" => Severity::Debug,
+            code => return code.parse().ok()
+                .and_then(Severity::from_code)
+                .ok_or_else(|| ParsePriorityError::new("severity", s)),
+        })
+    }
+}
+
+/// Error returned when a syslog severity or facility can't be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsePriorityError {
+    kind: &'static str,
+    value: String,
+}
+
+impl ParsePriorityError {
+    fn new(kind: &'static str, value: &str) -> ParsePriorityError {
+        ParsePriorityError { kind, value: value.to_owned() }
+    }
+}
+
+impl fmt::Display for ParsePriorityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid syslog {}: {:?}", self.kind, self.value)
+    }
+}
+
+impl error::Error for ParsePriorityError {}
+
+/// Syslog facility, the subsystem a message originates from
+///
+/// Userspace programs writing to `/dev/kmsg` are not allowed to use
+/// the `kern` facility, the kernel rewrites such messages to `user`.
+/// Codes without a standard name are kept as `Other`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Facility {
+    /// Kernel messages
+    Kern,
+    /// User-level messages
+    User,
+    /// Mail system
+    Mail,
+    /// System daemons
+    Daemon,
+    /// Security/authorization messages
+    Auth,
+    /// Messages generated internally by syslogd
+    Syslog,
+    /// Line printer subsystem
+    Lpr,
+    /// Network news subsystem
+    News,
+    /// UUCP subsystem
+    Uucp,
+    /// Clock daemon
+    Cron,
+    /// Private security/authorization messages
+    AuthPriv,
+    /// FTP daemon
+    Ftp,
+    /// NTP subsystem
+    Ntp,
+    /// Log audit
+    Security,
+    /// Log alert
+    Console,
+    /// Scheduling daemon
+    SolarisCron,
+    /// Locally used facility 0
+    Local0,
+    /// Locally used facility 1
+    Local1,
+    /// Locally used facility 2
+    Local2,
+    /// Locally used facility 3
+    Local3,
+    /// Locally used facility 4
+    Local4,
+    /// Locally used facility 5
+    Local5,
+    /// Locally used facility 6
+    Local6,
+    /// Locally used facility 7
+    Local7,
+    /// Facility code without a standard name, userspace may write
+    /// any `<N>` prefix to `/dev/kmsg` and the kernel keeps up to
+    /// 7 bits of facility
+    ///
+    /// Decoding functions produce it only for codes 24 to 127. Built
+    /// by hand with a named code it doesn't compare equal to the named
+    /// facility, with a code above 127 it is truncated by the kernel.
+    Other(u8),
+}
+
+impl Facility {
+    /// All facilities, ordered by their codes
+    pub const ALL: [Facility; 24] = [
+        Facility::Kern,
+        Facility::User,
+        Facility::Mail,
+        Facility::Daemon,
+        Facility::Auth,
+        Facility::Syslog,
+        Facility::Lpr,
+        Facility::News,
+        Facility::Uucp,
+        Facility::Cron,
+        Facility::AuthPriv,
+        Facility::Ftp,
+        Facility::Ntp,
+        Facility::Security,
+        Facility::Console,
+        Facility::SolarisCron,
+        Facility::Local0,
+        Facility::Local1,
+        Facility::Local2,
+        Facility::Local3,
+        Facility::Local4,
+        Facility::Local5,
+        Facility::Local6,
+        Facility::Local7,
+    ];
+
+    /// Get named facility from its numeric code
+    pub fn from_code(code: u8) -> Option<Facility> {
+        Facility::ALL.get(code as usize).cloned()
+    }
+
+    /// Numeric facility code
+    pub fn code(self) -> u8 {
+        match self {
+            Facility::Other(code) => code,
+            facility => Facility::ALL.iter().position(|&f| f == facility).unwrap() as u8,
+        }
+    }
+
+    /// Short syslog name of the facility (`kern`, `user`, `daemon`, ...),
+    /// `unknown` for `Other` codes
+    pub fn name(self) -> &'static str {
+        match self {
+            Facility::Kern => "kern",
+            Facility::User => "user",
+            Facility::Mail => "mail",
+            Facility::Daemon => "daemon",
+            Facility::Auth => "auth",
+            Facility::Syslog => "syslog",
+            Facility::Lpr => "lpr",
+            Facility::News => "news",
+            Facility::Uucp => "uucp",
+            Facility::Cron => "cron",
+            Facility::AuthPriv => "authpriv",
+            Facility::Ftp => "ftp",
+            Facility::Ntp => "ntp",
+            Facility::Security => "security",
+            Facility::Console => "console",
+            Facility::SolarisCron => "solaris-cron",
+            Facility::Local0 => "local0",
+            Facility::Local1 => "local1",
+            Facility::Local2 => "local2",
+            Facility::Local3 => "local3",
+            Facility::Local4 => "local4",
+            Facility::Local5 => "local5",
+            Facility::Local6 => "local6",
+            Facility::Local7 => "local7",
+            Facility::Other(_) => "unknown",
+        }
+    }
+}
+
+impl fmt::Display for Facility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Facility::Other(code) => write!(f, "{}", code),
+            facility => f.write_str(facility.name()),
+        }
+    }
+}
+
+impl FromStr for Facility {
+    type Err = ParsePriorityError;
+
+    /// Parse facility from its syslog name or numeric code
+    fn from_str(s: &str) -> Result<Facility, ParsePriorityError> {
+        let s = s.trim();
+        let name = s.to_ascii_lowercase();
+        Facility::ALL.iter().cloned()
+            .find(|facility| facility.name() == name)
+            .or_else(|| name.parse().ok().and_then(Facility::from_code))
+            .ok_or_else(|| ParsePriorityError::new("facility", s))
+    }
+}
+
+/// Largest facility code the kernel keeps
+const FACILITY_MAX: u8 = 127;
+
+/// Syslog priority, combination of facility and severity
+/// encoded as `facility * 8 + severity`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Priority {
+    /// Message facility
+    pub facility: Facility,
+    /// Message severity
+    pub severity: Severity,
+}
+
+impl Priority {
+    /// Create priority from facility and severity
+    pub fn new(facility: Facility, severity: Severity) -> Priority {
+        Priority { facility, severity }
+    }
+
+    /// Decode priority from its numeric code, facilities without
+    /// a standard name are kept as `Facility::Other`
+    ///
+    /// Returns `None` for codes the kernel would truncate,
+    /// with facility not fitting into 7 bits.
+    pub fn from_code(code: u16) -> Option<Priority> {
+        if code >> 3 > u16::from(FACILITY_MAX) {
+            return None;
+        }
+        let facility = (code >> 3) as u8;
+        Some(Priority {
+            facility: Facility::from_code(facility).unwrap_or(Facility::Other(facility)),
+            severity: Severity::from_code((code & 7) as u8).unwrap(),
+        })
+    }
+
+    /// Numeric priority code, as written in `<N>` message prefix
+    pub fn code(self) -> u16 {
+        u16::from(self.facility.code()) << 3 | u16::from(self.severity.code())
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.facility, self.severity)
+    }
+}
+
+/// Mapping of `log` levels to syslog severities
+///
+/// The default mapping is the conventional one: `Error` to `err`,
+/// `Warn` to `warning`, `Info` to `info` and both `Debug`
+/// and `Trace` to `debug`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeverityMap([Severity; 5]);
+
+impl SeverityMap {
+    /// Severity used for records with given level
+    pub fn get(&self, level: Level) -> Severity {
+        self.0[level as usize - 1]
+    }
+
+    /// Set severity used for records with given level
+    pub fn set(&mut self, level: Level, severity: Severity) {
+        self.0[level as usize - 1] = severity;
+    }
+}
+
+impl Default for SeverityMap {
+    fn default() -> SeverityMap {
+        SeverityMap([
+            Severity::Error,
+            Severity::Warning,
+            Severity::Info,
+            Severity::Debug,
+            Severity::Debug,
+        ])
+    }
+}
+
+thread_local! {
+    static SEVERITY: Cell<Option<Severity>> = const { Cell::new(None) };
+}
+
+/// Run `f` with all records logged from it on the current thread
+/// raised (or lowered) to `severity`, regardless of the severity map
+///
+/// This is what `emerg!`, `alert!` and `crit!` macros use.
+pub fn with_severity<F: FnOnce() -> R, R>(severity: Severity, f: F) -> R {
+    struct Reset(Option<Severity>);
+
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            SEVERITY.with(|s| s.set(self.0));
+        }
+    }
+
+    let _reset = Reset(SEVERITY.with(|s| s.replace(Some(severity))));
+    f()
+}
+
+/// Severity set with `with_severity()` for the current thread, if any
+pub(crate) fn severity_override() -> Option<Severity> {
+    SEVERITY.with(|s| s.get())
+}
+
+/// Log a message with `emerg` severity (at `Error` level)
+#[macro_export]
+macro_rules! emerg {
+    (target: $target:expr, $($arg:tt)+) => (
+        $crate::with_severity($crate::Severity::Emergency, || $crate::__log!(target: $target, $crate::__Level::Error, $($arg)+))
+    );
+    ($($arg:tt)+) => (
+        $crate::with_severity($crate::Severity::Emergency, || $crate::__log!($crate::__Level::Error, $($arg)+))
+    );
+}
+
+/// Log a message with `alert` severity (at `Error` level)
+#[macro_export]
+macro_rules! alert {
+    (target: $target:expr, $($arg:tt)+) => (
+        $crate::with_severity($crate::Severity::Alert, || $crate::__log!(target: $target, $crate::__Level::Error, $($arg)+))
+    );
+    ($($arg:tt)+) => (
+        $crate::with_severity($crate::Severity::Alert, || $crate::__log!($crate::__Level::Error, $($arg)+))
+    );
+}
+
+/// Log a message with `crit` severity (at `Error` level)
+#[macro_export]
+macro_rules! crit {
+    (target: $target:expr, $($arg:tt)+) => (
+        $crate::with_severity($crate::Severity::Critical, || $crate::__log!(target: $target, $crate::__Level::Error, $($arg)+))
+    );
+    ($($arg:tt)+) => (
+        $crate::with_severity($crate::Severity::Critical, || $crate::__log!($crate::__Level::Error, $($arg)+))
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+
+    use super::{severity_override, with_severity, Facility, Priority, Severity, SeverityMap};
+
+    #[test]
+    fn parse_severity() {
+        assert_eq!("crit".parse(), Ok(Severity::Critical));
+        assert_eq!("WARNING".parse(), Ok(Severity::Warning));
+        assert_eq!("0".parse(), Ok(Severity::Emergency));
+        assert!("8".parse::<Severity>().is_err());
+        assert_eq!("loud".parse::<Severity>().unwrap_err().to_string(), "invalid syslog severity: \"loud\"");
+    }
+
+    #[test]
+    fn parse_facility() {
+        assert_eq!("daemon".parse(), Ok(Facility::Daemon));
+        assert_eq!("Local7".parse(), Ok(Facility::Local7));
+        assert_eq!("1".parse(), Ok(Facility::User));
+        assert!("24".parse::<Facility>().is_err());
+        assert_eq!("usr".parse::<Facility>().unwrap_err().to_string(), "invalid syslog facility: \"usr\"");
+    }
+
+    #[test]
+    fn priority_codes() {
+        let priority = Priority::new(Facility::Daemon, Severity::Warning);
+        assert_eq!(priority.code(), 28);
+        assert_eq!(Priority::from_code(28), Some(priority));
+        assert_eq!(Priority::from_code(191), Some(Priority::new(Facility::Local7, Severity::Debug)));
+        assert_eq!(Priority::from_code(206), Some(Priority::new(Facility::Other(25), Severity::Info)));
+        assert_eq!(Priority::from_code(206).unwrap().code(), 206);
+        assert_eq!(Priority::from_code(1023).unwrap().to_string(), "127.debug");
+        assert_eq!(Priority::from_code(1024), None);
+        assert_eq!(Priority::from_code(2054), None);
+        assert_eq!(priority.to_string(), "daemon.warning");
+    }
+
+    #[test]
+    fn default_map() {
+        let mut map = SeverityMap::default();
+        assert_eq!(map.get(Level::Error), Severity::Error);
+        assert_eq!(map.get(Level::Info), Severity::Info);
+        assert_eq!(map.get(Level::Trace), Severity::Debug);
+        map.set(Level::Info, Severity::Notice);
+        assert_eq!(map.get(Level::Info), Severity::Notice);
+    }
+
+    #[test]
+    fn nested_override() {
+        assert_eq!(severity_override(), None);
+        with_severity(Severity::Alert, || {
+            with_severity(Severity::Emergency, || assert_eq!(severity_override(), Some(Severity::Emergency)));
+            assert_eq!(severity_override(), Some(Severity::Alert));
+        });
+        assert_eq!(severity_override(), None);
+    }
+}
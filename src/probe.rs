@@ -0,0 +1,315 @@
+use std::error;
+use std::ffi::CStr;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use libc;
+
+use DEFAULT_DEVICE;
+
+/// Path to the userspace kmsg logging mode, `on`, `off` or `ratelimit`
+pub const PRINTK_DEVKMSG_PATH: &str = "/proc/sys/kernel/printk_devkmsg";
+
+/// Path to the flag restricting kernel log reads to `CAP_SYSLOG` holders
+pub const DMESG_RESTRICT_PATH: &str = "/proc/sys/kernel/dmesg_restrict";
+
+/// Path to the console log levels
+pub const PRINTK_PATH: &str = "/proc/sys/kernel/printk";
+
+/// Path to the status of the current process, including its capabilities
+const STATUS_PATH: &str = "/proc/self/status";
+
+/// Capability number of `CAP_SYSLOG`
+const CAP_SYSLOG: u32 = 34;
+
+/// How the kernel treats messages written to `/dev/kmsg` by userspace
+/// (`printk.devkmsg` boot parameter)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DevkmsgMode {
+    /// All messages are logged
+    On,
+    /// All messages are silently dropped
+    Off,
+    /// Messages over 10 per 5 seconds are silently dropped (default)
+    Ratelimit,
+}
+
+impl FromStr for DevkmsgMode {
+    type Err = ParseSettingError;
+
+    fn from_str(s: &str) -> Result<DevkmsgMode, ParseSettingError> {
+        match s.trim() {
+            "on" => Ok(DevkmsgMode::On),
+            "off" => Ok(DevkmsgMode::Off),
+            "ratelimit" => Ok(DevkmsgMode::Ratelimit),
+            _ => Err(ParseSettingError::new("printk_devkmsg mode", s)),
+        }
+    }
+}
+
+impl fmt::Display for DevkmsgMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            DevkmsgMode::On => "on",
+            DevkmsgMode::Off => "off",
+            DevkmsgMode::Ratelimit => "ratelimit",
+        })
+    }
+}
+
+/// Console log levels from `/proc/sys/kernel/printk`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConsoleLevels {
+    /// Messages with severity code below this are printed on console
+    pub console: u8,
+    /// Severity of messages logged without one
+    pub default_message: u8,
+    /// Lowest value console level can be set to
+    pub minimum_console: u8,
+    /// Default value of console level
+    pub default_console: u8,
+}
+
+impl FromStr for ConsoleLevels {
+    type Err = ParseSettingError;
+
+    fn from_str(s: &str) -> Result<ConsoleLevels, ParseSettingError> {
+        let levels = s.split_whitespace()
+            .map(|level| level.parse())
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| ParseSettingError::new("console loglevels", s))?;
+        match levels[..] {
+            [console, default_message, minimum_console, default_console] => Ok(ConsoleLevels {
+                console,
+                default_message,
+                minimum_console,
+                default_console,
+            }),
+            _ => Err(ParseSettingError::new("console loglevels", s)),
+        }
+    }
+}
+
+/// Error returned when a kernel logging setting can't be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseSettingError {
+    kind: &'static str,
+    value: String,
+}
+
+impl ParseSettingError {
+    fn new(kind: &'static str, value: &str) -> ParseSettingError {
+        ParseSettingError { kind, value: value.trim().to_owned() }
+    }
+}
+
+impl fmt::Display for ParseSettingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid {}: {:?}", self.kind, self.value)
+    }
+}
+
+impl error::Error for ParseSettingError {}
+
+/// Report on kmsg device access and kernel logging settings,
+/// see `probe()`
+///
+/// Settings which can't be read are `None`. The report prints
+/// as a human-readable summary followed by detected problems.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Probe {
+    /// Probed device
+    pub device: PathBuf,
+    /// Whether the device exists
+    pub exists: bool,
+    /// Result of opening the device for writing
+    pub writable: Result<(), io::ErrorKind>,
+    /// Result of opening the device for reading
+    pub readable: Result<(), io::ErrorKind>,
+    /// Userspace logging mode
+    pub devkmsg: Option<DevkmsgMode>,
+    /// Whether reading the kernel log requires `CAP_SYSLOG`
+    pub dmesg_restrict: Option<bool>,
+    /// Console log levels
+    pub console_levels: Option<ConsoleLevels>,
+    /// Whether the process has `CAP_SYSLOG` effective capability
+    pub cap_syslog: Option<bool>,
+    /// Kernel release, as `uname -r` prints it
+    pub kernel_version: Option<String>,
+}
+
+impl Probe {
+    /// Explanations of why messages may get lost or can't be read back
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let device = self.device.display();
+
+        if !self.exists {
+            problems.push(format!("{} doesn't exist, is /dev mounted?", device));
+        } else if let Err(kind) = self.writable {
+            problems.push(format!("{} is not writable: {}", device, kind));
+        }
+        match self.devkmsg {
+            Some(DevkmsgMode::Off) => problems.push("printk_devkmsg is off, userspace messages are dropped".to_owned()),
+            Some(DevkmsgMode::Ratelimit) => problems.push(
+                "printk_devkmsg is ratelimit, messages over 10 per 5 seconds are dropped".to_owned()),
+            _ => (),
+        }
+        if self.exists && self.readable.is_err() {
+            let hint = match (self.dmesg_restrict, self.cap_syslog) {
+                (Some(true), Some(false)) => ", dmesg_restrict requires CAP_SYSLOG",
+                _ => "",
+            };
+            problems.push(format!("{} is not readable{}", device, hint));
+        }
+        problems
+    }
+}
+
+impl fmt::Display for Probe {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn access(result: &Result<(), io::ErrorKind>) -> String {
+            match *result {
+                Ok(()) => "yes".to_owned(),
+                Err(kind) => format!("no ({})", kind),
+            }
+        }
+        fn show<T: fmt::Display>(value: &Option<T>) -> String {
+            value.as_ref().map_or_else(|| "unknown".to_owned(), |value| value.to_string())
+        }
+
+        writeln!(f, "device: {}", self.device.display())?;
+        writeln!(f, "exists: {}", if self.exists { "yes" } else { "no" })?;
+        writeln!(f, "writable: {}", access(&self.writable))?;
+        writeln!(f, "readable: {}", access(&self.readable))?;
+        writeln!(f, "printk_devkmsg: {}", show(&self.devkmsg))?;
+        writeln!(f, "dmesg_restrict: {}", show(&self.dmesg_restrict.map(|r| r as u8)))?;
+        match self.console_levels {
+            Some(levels) => writeln!(f, "console loglevels: {} {} {} {}", levels.console,
+                levels.default_message, levels.minimum_console, levels.default_console)?,
+            None => writeln!(f, "console loglevels: unknown")?,
+        }
+        writeln!(f, "CAP_SYSLOG: {}", show(&self.cap_syslog.map(|c| if c { "yes" } else { "no" })))?;
+        writeln!(f, "kernel: {}", show(&self.kernel_version))?;
+        for problem in self.problems() {
+            writeln!(f, "problem: {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+/// Probe `/dev/kmsg` access and kernel logging settings
+/// to diagnose why messages don't show up
+///
+/// ```rust,no_run
+/// extern crate kernlog;
+///
+/// fn main() {
+///     let probe = kernlog::probe();
+///     print!("{}", probe);
+///     if !probe.problems().is_empty() {
+///         std::process::exit(1);
+///     }
+/// }
+/// ```
+pub fn probe() -> Probe {
+    probe_device(DEFAULT_DEVICE)
+}
+
+/// Probe access to device at `path` and kernel logging settings
+pub fn probe_device<P: AsRef<Path>>(path: P) -> Probe {
+    let path = path.as_ref();
+    Probe {
+        device: path.to_path_buf(),
+        exists: path.exists(),
+        writable: OpenOptions::new().append(true).open(path).map(drop).map_err(|err| err.kind()),
+        readable: OpenOptions::new().read(true).open(path).map(drop).map_err(|err| err.kind()),
+        devkmsg: read_setting(PRINTK_DEVKMSG_PATH),
+        dmesg_restrict: read_setting::<u8>(DMESG_RESTRICT_PATH).map(|restrict| restrict != 0),
+        console_levels: read_setting(PRINTK_PATH),
+        cap_syslog: fs::read_to_string(STATUS_PATH).ok().and_then(|status| has_capability(&status, CAP_SYSLOG)),
+        kernel_version: kernel_release(),
+    }
+}
+
+fn read_setting<T: FromStr>(path: &str) -> Option<T> {
+    fs::read_to_string(path).ok().and_then(|value| value.trim().parse().ok())
+}
+
+/// Check effective capability from `/proc/<pid>/status` contents
+fn has_capability(status: &str, capability: u32) -> Option<bool> {
+    let caps = status.lines().find_map(|line| line.strip_prefix("CapEff:"))?;
+    let caps = u64::from_str_radix(caps.trim(), 16).ok()?;
+    Some(caps & (1 << capability) != 0)
+}
+
+fn kernel_release() -> Option<String> {
+    let mut uts: libc::utsname = unsafe { mem::zeroed() };
+    if unsafe { libc::uname(&mut uts) } != 0 {
+        return None;
+    }
+    let release = unsafe { CStr::from_ptr(uts.release.as_ptr()) };
+    Some(release.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::path::PathBuf;
+
+    use super::{has_capability, probe, probe_device, ConsoleLevels, DevkmsgMode, Probe};
+
+    #[test]
+    fn parse_settings() {
+        assert_eq!("ratelimit\n".parse(), Ok(DevkmsgMode::Ratelimit));
+        assert_eq!("maybe".parse::<DevkmsgMode>().unwrap_err().to_string(), "invalid printk_devkmsg mode: \"maybe\"");
+        assert_eq!("4\t4\t1\t7\n".parse(), Ok(ConsoleLevels {
+            console: 4,
+            default_message: 4,
+            minimum_console: 1,
+            default_console: 7,
+        }));
+        assert_eq!("4 4 1".parse::<ConsoleLevels>().unwrap_err().to_string(), "invalid console loglevels: \"4 4 1\"");
+        assert!("4 4 x 7".parse::<ConsoleLevels>().is_err());
+
+        let status = "Name:\tinit\nCapInh:\t0000000000000000\nCapEff:\t0000000400000000\n";
+        assert_eq!(has_capability(status, 34), Some(true));
+        assert_eq!(has_capability(status, 21), Some(false));
+        assert_eq!(has_capability("Name:\tinit\n", 34), None);
+    }
+
+    #[test]
+    fn problems() {
+        let probe = Probe {
+            device: PathBuf::from("/dev/kmsg"),
+            exists: true,
+            writable: Err(io::ErrorKind::PermissionDenied),
+            readable: Err(io::ErrorKind::PermissionDenied),
+            devkmsg: Some(DevkmsgMode::Off),
+            dmesg_restrict: Some(true),
+            console_levels: None,
+            cap_syslog: Some(false),
+            kernel_version: None,
+        };
+        assert_eq!(probe.problems(), vec![
+            "/dev/kmsg is not writable: permission denied",
+            "printk_devkmsg is off, userspace messages are dropped",
+            "/dev/kmsg is not readable, dmesg_restrict requires CAP_SYSLOG",
+        ]);
+        assert!(probe.to_string().contains("console loglevels: unknown\n"));
+
+        let missing = probe_device("/nonexistent/kmsg");
+        assert_eq!(missing.problems()[0], "/nonexistent/kmsg doesn't exist, is /dev mounted?");
+    }
+
+    #[test]
+    fn probe_kmsg() {
+        let probe = probe();
+        assert!(probe.kernel_version.is_some());
+        assert_eq!(probe.exists, probe.device.exists());
+    }
+}
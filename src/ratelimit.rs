@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::mem;
+use std::time::{Duration, Instant};
+
+/// Token bucket rate limit: at most `burst` records at once,
+/// refilled at the rate of `burst` records per `interval`
+///
+/// A `burst` of 0 is treated as 1, so logging is never suppressed
+/// for good.
+///
+/// The kernel's own `printk_devkmsg=ratelimit` allows bursts of 10
+/// records per 5 seconds and drops the rest silently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Maximum number of records written at once
+    pub burst: u32,
+    /// Time to refill the whole burst
+    pub interval: Duration,
+}
+
+impl RateLimit {
+    /// Create rate limit of `burst` records per `interval`
+    pub fn new(burst: u32, interval: Duration) -> RateLimit {
+        RateLimit { burst, interval }
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    limit: RateLimit,
+    tokens: f64,
+    updated: Instant,
+    suppressed: u64,
+}
+
+impl Bucket {
+    fn new(limit: RateLimit, now: Instant) -> Bucket {
+        let limit = RateLimit::new(limit.burst.max(1), limit.interval);
+        Bucket {
+            limit,
+            tokens: limit.burst as f64,
+            updated: now,
+            suppressed: 0,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        let interval = self.limit.interval.as_secs_f64();
+        let burst = self.limit.burst as f64;
+        self.tokens = if interval > 0.0 { (self.tokens + elapsed / interval * burst).min(burst) } else { burst };
+        self.updated = now;
+        self.tokens >= 1.0
+    }
+}
+
+/// Global and per-target token buckets with counts of suppressed records
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    global: Option<Bucket>,
+    target_limit: Option<RateLimit>,
+    targets: HashMap<String, Bucket>,
+    /// Records suppressed by the global limit, by target
+    suppressed: HashMap<String, u64>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(global: Option<RateLimit>, target_limit: Option<RateLimit>) -> RateLimiter {
+        let now = Instant::now();
+        RateLimiter {
+            global: global.map(|limit| Bucket::new(limit, now)),
+            target_limit,
+            targets: HashMap::new(),
+            suppressed: HashMap::new(),
+        }
+    }
+
+    /// Check if record from `target` may be written, returning counts
+    /// of records suppressed by target since logging was last allowed,
+    /// or `None` if this record has to be suppressed too
+    pub(crate) fn check(&mut self, target: &str, now: Instant) -> Option<Vec<(String, u64)>> {
+        if let Some(limit) = self.target_limit {
+            if !self.targets.contains_key(target) {
+                self.targets.insert(target.to_owned(), Bucket::new(limit, now));
+            }
+            let bucket = self.targets.get_mut(target).unwrap();
+            if !bucket.refill(now) {
+                bucket.suppressed += 1;
+                return None;
+            }
+        }
+
+        if let Some(ref mut bucket) = self.global {
+            if !bucket.refill(now) {
+                *self.suppressed.entry(target.to_owned()).or_insert(0) += 1;
+                return None;
+            }
+            bucket.tokens -= 1.0;
+        }
+
+        if let Some(bucket) = self.targets.get_mut(target) {
+            bucket.tokens -= 1.0;
+            if bucket.suppressed > 0 {
+                *self.suppressed.entry(target.to_owned()).or_insert(0) += mem::replace(&mut bucket.suppressed, 0);
+            }
+        }
+
+        let mut summaries: Vec<(String, u64)> = self.suppressed.drain().collect();
+        summaries.sort();
+        Some(summaries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{RateLimit, RateLimiter};
+
+    #[test]
+    fn global_limit() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new(Some(RateLimit::new(2, Duration::from_secs(1))), None);
+        assert_eq!(limiter.check("a", start), Some(vec![]));
+        assert_eq!(limiter.check("b", start), Some(vec![]));
+        assert_eq!(limiter.check("a", start), None);
+        assert_eq!(limiter.check("b", start), None);
+        assert_eq!(limiter.check("b", start + Duration::from_millis(100)), None);
+
+        let summaries = vec![("a".to_owned(), 1), ("b".to_owned(), 2)];
+        assert_eq!(limiter.check("c", start + Duration::from_millis(500)), Some(summaries));
+        assert_eq!(limiter.check("c", start + Duration::from_millis(500)), None);
+    }
+
+    #[test]
+    fn target_limit() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new(None, Some(RateLimit::new(1, Duration::from_secs(10))));
+        assert_eq!(limiter.check("noisy", start), Some(vec![]));
+        assert_eq!(limiter.check("noisy", start), None);
+        assert_eq!(limiter.check("noisy", start), None);
+        assert_eq!(limiter.check("quiet", start), Some(vec![]));
+
+        let later = start + Duration::from_secs(10);
+        assert_eq!(limiter.check("noisy", later), Some(vec![("noisy".to_owned(), 2)]));
+        assert_eq!(limiter.check("quiet", later), Some(vec![]));
+    }
+
+    #[test]
+    fn zero_burst() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new(Some(RateLimit::new(0, Duration::from_secs(1))), None);
+        assert_eq!(limiter.check("a", start), Some(vec![]));
+        assert_eq!(limiter.check("a", start), None);
+        assert_eq!(limiter.check("a", start + Duration::from_secs(1)), Some(vec![("a".to_owned(), 1)]));
+    }
+}
@@ -0,0 +1,682 @@
+use std::error;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::Path;
+use std::str;
+use std::time::Duration;
+
+use libc;
+
+use cursor::{self, Cursor, Resume};
+use {Facility, Priority, Severity, DEFAULT_DEVICE};
+
+/// Size of the read buffer, the kernel refuses to return a record
+/// which doesn't fit into the buffer passed to `read()`
+const READ_BUFFER_SIZE: usize = 16 * 1024;
+
+/// Record read from `/dev/kmsg`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KmsgRecord {
+    /// Record facility
+    pub facility: Facility,
+    /// Record severity
+    pub severity: Severity,
+    /// Record sequence number, increasing by one for every record
+    pub sequence: u64,
+    /// Time since boot in microseconds
+    pub timestamp: u64,
+    /// Record flags
+    pub flags: Flags,
+    /// Message text with kernel escapes (`\xNN`) decoded
+    pub message: String,
+    /// Dictionary of additional properties, like `SUBSYSTEM` and `DEVICE`
+    pub dict: Vec<(String, String)>,
+}
+
+/// Flags field of a kmsg record
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Flags {
+    /// Whether the record continues a previous one
+    pub continuation: Continuation,
+    /// Thread or CPU which logged the record, if the kernel was built
+    /// with `CONFIG_PRINTK_CALLER`
+    pub caller: Option<Caller>,
+}
+
+/// Continuation state of a kmsg record (legacy `KERN_CONT` fragments)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Continuation {
+    /// Standalone record (`-` flag)
+    #[default]
+    Standalone,
+    /// First fragment of a continued line (`c` flag)
+    Start,
+    /// Following fragment of a continued line (`+` flag)
+    Continued,
+}
+
+/// Origin of a kmsg record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Caller {
+    /// Logged by thread with given id (`caller=T123`)
+    Thread(u32),
+    /// Logged by given CPU in interrupt context (`caller=C1`)
+    Cpu(u32),
+}
+
+impl KmsgRecord {
+    /// Record priority, combined facility and severity
+    pub fn priority(&self) -> Priority {
+        Priority::new(self.facility, self.severity)
+    }
+
+    /// Value of dictionary property with given key
+    pub fn property(&self, key: &str) -> Option<&str> {
+        self.dict.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    /// Parse record in `/dev/kmsg` format:
+    /// `pri,seq,usec,flags[,caller=...];message\n[ KEY=value\n]...`
+    pub fn parse(data: &[u8]) -> Result<KmsgRecord, ParseRecordError> {
+        let data = str::from_utf8(data).map_err(|_| ParseRecordError::new("record is not UTF-8"))?;
+        let semicolon = data.find(';').ok_or_else(|| ParseRecordError::new("missing ';'"))?;
+        let (prefix, rest) = (&data[..semicolon], &data[semicolon + 1..]);
+
+        let mut fields = prefix.split(',');
+        let priority = fields.next()
+            .and_then(|f| f.parse::<u16>().ok())
+            .and_then(Priority::from_code)
+            .ok_or_else(|| ParseRecordError::new("invalid priority"))?;
+        let sequence = fields.next()
+            .and_then(|f| f.parse().ok())
+            .ok_or_else(|| ParseRecordError::new("invalid sequence number"))?;
+        let timestamp = fields.next()
+            .and_then(|f| f.parse().ok())
+            .ok_or_else(|| ParseRecordError::new("invalid timestamp"))?;
+        let continuation = match fields.next() {
+            Some("-") => Continuation::Standalone,
+            Some("c") => Continuation::Start,
+            Some("+") => Continuation::Continued,
+            _ => return Err(ParseRecordError::new("invalid flags")),
+        };
+
+        let mut caller = None;
+        for field in fields {
+            if let Some(value) = field.strip_prefix("caller=") {
+                caller = parse_caller(value);
+            }
+        }
+
+        let mut lines = rest.split('\n');
+        let message = unescape(lines.next().unwrap_or(""));
+        let dict = lines
+            .filter_map(|line| line.strip_prefix(' '))
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.to_owned(), unescape(value)))
+            .collect();
+
+        Ok(KmsgRecord {
+            facility: priority.facility,
+            severity: priority.severity,
+            sequence,
+            timestamp,
+            flags: Flags { continuation, caller },
+            message,
+            dict,
+        })
+    }
+}
+
+fn parse_caller(value: &str) -> Option<Caller> {
+    let (kind, id) = value.split_at(value.find(|c: char| c.is_ascii_digit())?);
+    let id = id.parse().ok()?;
+    match kind {
+        "T" => Some(Caller::Thread(id)),
+        "C" => Some(Caller::Cpu(id)),
+        _ => None,
+    }
+}
+
+/// Decode `\xNN` escapes the kernel uses for non-printable bytes
+fn unescape(text: &str) -> String {
+    if !text.contains("\\x") {
+        return text.to_owned();
+    }
+
+    let bytes = text.as_bytes();
+    let mut buf = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes.get(i..i + 4)
+            .filter(|esc| esc.starts_with(b"\\x"))
+            .and_then(|esc| str::from_utf8(&esc[2..]).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(b) => {
+                buf.push(b);
+                i += 4;
+            }
+            None => {
+                buf.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&buf).into_owned()
+}
+
+/// Error returned when a kmsg record can't be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRecordError(&'static str);
+
+impl ParseRecordError {
+    fn new(reason: &'static str) -> ParseRecordError {
+        ParseRecordError(reason)
+    }
+}
+
+impl fmt::Display for ParseRecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid kmsg record: {}", self.0)
+    }
+}
+
+impl error::Error for ParseRecordError {}
+
+impl From<ParseRecordError> for io::Error {
+    fn from(err: ParseRecordError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// Position in the kernel ring buffer to read records from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    /// Oldest record still in the buffer (`SEEK_SET`)
+    Start,
+    /// After the newest record, only records logged from now on
+    /// will be read (`SEEK_END`)
+    End,
+    /// First record after the last `dmesg --clear`, this is where
+    /// `syslog(2)` based readers start (`SEEK_DATA`)
+    AfterClear,
+}
+
+/// Event read from the kernel ring buffer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// Record was read
+    Record(KmsgRecord),
+    /// Given number of records were overwritten in the ring buffer
+    /// before they could be read (the kernel reports `EPIPE` to the reader)
+    Lost(u64),
+}
+
+type RecordFilter = Box<dyn Fn(&KmsgRecord) -> bool + Send>;
+
+/// Reader of kernel log records from `/dev/kmsg`
+///
+/// By default it iterates over all records currently in the kernel
+/// ring buffer and stops at its end:
+///
+/// ```rust,no_run
+/// extern crate kernlog;
+///
+/// use kernlog::KmsgReader;
+///
+/// fn main() {
+///     for record in KmsgReader::open().unwrap() {
+///         let record = record.unwrap();
+///         println!("[{}] <{}> {}", record.timestamp, record.severity, record.message);
+///     }
+/// }
+/// ```
+///
+/// In follow mode it waits for new records instead, like `dmesg -w`,
+/// reporting records lost in between as events:
+///
+/// ```rust,no_run
+/// extern crate kernlog;
+///
+/// use kernlog::{Event, KmsgReader, Position};
+///
+/// fn main() {
+///     let mut reader = KmsgReader::open().unwrap();
+///     reader.seek(Position::End).unwrap();
+///     reader.set_follow(true);
+///     for event in reader.events() {
+///         match event.unwrap() {
+///             Event::Record(record) => println!("{}", record.message),
+///             Event::Lost(count) => eprintln!("lost {} records", count),
+///         }
+///     }
+/// }
+/// ```
+pub struct KmsgReader {
+    file: File,
+    buf: Vec<u8>,
+    follow: bool,
+    filter: Option<RecordFilter>,
+    next_sequence: Option<u64>,
+    pending: Option<KmsgRecord>,
+    boot_id: Option<String>,
+}
+
+impl KmsgReader {
+    /// Open `/dev/kmsg` for reading
+    pub fn open() -> io::Result<KmsgReader> {
+        KmsgReader::open_path(DEFAULT_DEVICE)
+    }
+
+    /// Open device at `path` for reading kmsg records
+    pub fn open_path<P: AsRef<Path>>(path: P) -> io::Result<KmsgReader> {
+        let file = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(path)?;
+        Ok(KmsgReader::from_file(file))
+    }
+
+    /// Read kmsg records from already opened file
+    ///
+    /// Iteration stops at the end of the buffer only if the file
+    /// was opened in non-blocking mode.
+    pub fn from_file(file: File) -> KmsgReader {
+        KmsgReader {
+            file,
+            buf: vec![0; READ_BUFFER_SIZE],
+            follow: false,
+            filter: None,
+            next_sequence: None,
+            pending: None,
+            boot_id: cursor::boot_id().ok(),
+        }
+    }
+
+    /// Set whether to wait for new records at the end of the buffer
+    /// instead of stopping there
+    pub fn set_follow(&mut self, follow: bool) {
+        self.follow = follow;
+    }
+
+    /// Whether reader waits for new records at the end of the buffer
+    pub fn follows(&self) -> bool {
+        self.follow
+    }
+
+    /// Set predicate records have to match to be read,
+    /// e.g. `|record| record.severity <= Severity::Warning`
+    pub fn set_filter<F: Fn(&KmsgRecord) -> bool + Send + 'static>(&mut self, filter: F) {
+        self.filter = Some(Box::new(filter));
+    }
+
+    /// Move reader to given position in the ring buffer
+    pub fn seek(&mut self, position: Position) -> io::Result<()> {
+        let whence = match position {
+            Position::Start => libc::SEEK_SET,
+            Position::End => libc::SEEK_END,
+            Position::AfterClear => libc::SEEK_DATA,
+        };
+        if unsafe { libc::lseek(self.file.as_raw_fd(), 0, whence) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        self.next_sequence = None;
+        self.pending = None;
+        Ok(())
+    }
+
+    /// Wait until a record is available for reading,
+    /// returning `false` if `timeout` elapsed first
+    pub fn wait(&self, timeout: Option<Duration>) -> io::Result<bool> {
+        if self.pending.is_some() {
+            return Ok(true);
+        }
+
+        let timeout = timeout.map_or(-1, |t| t.as_millis().min(i32::MAX as u128) as libc::c_int);
+        let mut fds = libc::pollfd {
+            fd: self.file.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        loop {
+            match unsafe { libc::poll(&mut fds, 1, timeout) } {
+                -1 => {
+                    let err = io::Error::last_os_error();
+                    if err.kind() != io::ErrorKind::Interrupted {
+                        return Err(err);
+                    }
+                }
+                0 => return Ok(false),
+                _ => return Ok(true),
+            }
+        }
+    }
+
+    /// Read next event, returning `None` at the end of the buffer
+    /// (unless in follow mode)
+    pub fn read_event(&mut self) -> io::Result<Option<Event>> {
+        loop {
+            match self.try_read_event() {
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    if !self.follow {
+                        return Ok(None);
+                    }
+                    self.wait(None)?;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Read next record, returning `None` at the end of the buffer
+    /// (unless in follow mode)
+    ///
+    /// Records overwritten in the ring buffer before they were read
+    /// are silently skipped, use `read_event()` to get notified of them.
+    pub fn read_record(&mut self) -> io::Result<Option<KmsgRecord>> {
+        loop {
+            match self.read_event()? {
+                Some(Event::Record(record)) => return Ok(Some(record)),
+                Some(Event::Lost(_)) => (),
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Iterate over events instead of records
+    pub fn events(&mut self) -> Events<'_> {
+        Events(self)
+    }
+
+    /// Cursor pointing after the last record read, if any
+    ///
+    /// The cursor can be saved and used to resume reading after
+    /// reader restart with `seek_cursor()`. A record already read from
+    /// the device but not returned yet, following a `Lost` event or
+    /// `seek_cursor()`, is covered by the cursor only once returned.
+    pub fn cursor(&self) -> Option<Cursor> {
+        match (&self.boot_id, self.next_sequence) {
+            (Some(boot_id), Some(next)) => Some(Cursor {
+                boot_id: boot_id.clone(),
+                sequence: next - 1,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Move reader right after the record `cursor` points to,
+    /// skipping all records read before
+    ///
+    /// If the system was rebooted since the cursor was taken, or the
+    /// records following the cursor were overwritten in the ring buffer,
+    /// reading continues from the oldest record available and the
+    /// outcome tells so.
+    ///
+    /// Fails if the boot id could not be read when the reader was
+    /// opened, as the cursor can't be checked against it.
+    pub fn seek_cursor(&mut self, cursor: &Cursor) -> io::Result<Resume> {
+        let boot_changed = match self.boot_id {
+            Some(ref boot_id) => *boot_id != cursor.boot_id,
+            None => return Err(io::Error::new(io::ErrorKind::NotFound, "boot id unavailable")),
+        };
+        self.seek(Position::Start)?;
+        if boot_changed {
+            return Ok(Resume::BootChanged);
+        }
+        self.skip_to(cursor.sequence)
+    }
+
+    fn skip_to(&mut self, sequence: u64) -> io::Result<Resume> {
+        loop {
+            match self.read_once() {
+                Ok(Some(ref record)) if record.sequence <= sequence => (),
+                Ok(Some(record)) => {
+                    let lost = record.sequence - sequence - 1;
+                    if self.matches(&record) {
+                        self.next_sequence = Some(sequence + 1);
+                        self.pending = Some(record);
+                    } else {
+                        self.next_sequence = Some(record.sequence + 1);
+                    }
+                    return Ok(if lost > 0 { Resume::Overwritten(lost) } else { Resume::Resumed });
+                }
+                Ok(None) => {
+                    self.next_sequence = Some(sequence + 1);
+                    return Ok(Resume::Resumed);
+                }
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    self.next_sequence = Some(sequence + 1);
+                    return Ok(Resume::Resumed);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Read next event without waiting, failing with `WouldBlock`
+    /// at the end of the buffer
+    pub(crate) fn try_read_event(&mut self) -> io::Result<Option<Event>> {
+        if let Some(record) = self.pending.take() {
+            self.next_sequence = Some(record.sequence + 1);
+            return Ok(Some(Event::Record(record)));
+        }
+
+        loop {
+            let record = match self.read_once()? {
+                Some(record) => record,
+                None => return Ok(None),
+            };
+
+            let lost = self.next_sequence.map_or(0, |expected| record.sequence.saturating_sub(expected));
+            let matches = self.matches(&record);
+
+            if lost > 0 {
+                // the cursor moves past the record only once it is returned
+                if matches {
+                    self.pending = Some(record);
+                } else {
+                    self.next_sequence = Some(record.sequence + 1);
+                }
+                return Ok(Some(Event::Lost(lost)));
+            }
+            self.next_sequence = Some(record.sequence + 1);
+            if matches {
+                return Ok(Some(Event::Record(record)));
+            }
+        }
+    }
+
+    fn read_once(&mut self) -> io::Result<Option<KmsgRecord>> {
+        loop {
+            match self.file.read(&mut self.buf) {
+                Ok(0) => return Ok(None),
+                Ok(len) => return Ok(Some(KmsgRecord::parse(&self.buf[..len])?)),
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => (),
+                // the kernel moves reader to the oldest available record,
+                // lost records are counted by the sequence number gap
+                Err(ref err) if err.raw_os_error() == Some(libc::EPIPE) => (),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn matches(&self, record: &KmsgRecord) -> bool {
+        self.filter.as_ref().is_none_or(|filter| filter(record))
+    }
+}
+
+impl fmt::Debug for KmsgReader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("KmsgReader")
+            .field("file", &self.file)
+            .field("follow", &self.follow)
+            .field("next_sequence", &self.next_sequence)
+            .field("boot_id", &self.boot_id)
+            .finish()
+    }
+}
+
+impl AsRawFd for KmsgReader {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+impl Iterator for KmsgReader {
+    type Item = io::Result<KmsgRecord>;
+
+    fn next(&mut self) -> Option<io::Result<KmsgRecord>> {
+        self.read_record().transpose()
+    }
+}
+
+/// Iterator over kmsg reader events, see `KmsgReader::events()`
+#[derive(Debug)]
+pub struct Events<'a>(&'a mut KmsgReader);
+
+impl<'a> Iterator for Events<'a> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<io::Result<Event>> {
+        self.0.read_event().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::io;
+    use std::os::fd::OwnedFd;
+    use std::os::unix::net::UnixDatagram;
+    use std::process;
+    use std::time::Duration;
+
+    use super::{Caller, Continuation, Event, KmsgReader, KmsgRecord, Position};
+    use {Cursor, Facility, Resume, Severity};
+
+    /// Reader getting one record per read from a datagram socket
+    fn datagram_reader(sequences: &[u64]) -> KmsgReader {
+        let (tx, rx) = UnixDatagram::pair().unwrap();
+        for seq in sequences {
+            tx.send(format!("6,{},0,-;record {}\n", seq, seq).as_bytes()).unwrap();
+        }
+        rx.set_nonblocking(true).unwrap();
+        let mut reader = KmsgReader::from_file(File::from(OwnedFd::from(rx)));
+        reader.boot_id = Some("boot".to_owned());
+        reader
+    }
+
+    #[test]
+    fn parse_record() {
+        let record = KmsgRecord::parse(b"6,339,5140900,-;NET: Registered protocol family 10\n").unwrap();
+        assert_eq!(record.facility, Facility::Kern);
+        assert_eq!(record.severity, Severity::Info);
+        assert_eq!(record.sequence, 339);
+        assert_eq!(record.timestamp, 5140900);
+        assert_eq!(record.flags.continuation, Continuation::Standalone);
+        assert_eq!(record.flags.caller, None);
+        assert_eq!(record.message, "NET: Registered protocol family 10");
+        assert!(record.dict.is_empty());
+    }
+
+    #[test]
+    fn parse_dict_and_caller() {
+        let data = b"30,1075,83126541,c,caller=T1;my-helper[1]: tab\\x09and \\xd1\\x8f\\x5cx41\n \
+                     SUBSYSTEM=pci\n DEVICE=+pci:0000:00:1f.2\n";
+        let record = KmsgRecord::parse(data).unwrap();
+        assert_eq!(record.facility, Facility::Daemon);
+        assert_eq!(record.severity, Severity::Info);
+        assert_eq!(record.flags.continuation, Continuation::Start);
+        assert_eq!(record.flags.caller, Some(Caller::Thread(1)));
+        assert_eq!(record.message, "my-helper[1]: tab\tand \u{44f}\\x41");
+        assert_eq!(record.property("SUBSYSTEM"), Some("pci"));
+        assert_eq!(record.property("DEVICE"), Some("+pci:0000:00:1f.2"));
+        assert_eq!(record.property("DRIVER"), None);
+
+        let record = KmsgRecord::parse(b"200,7,100,-;odd facility\n").unwrap();
+        assert_eq!(record.facility, Facility::Other(25));
+        assert_eq!(record.priority().code(), 200);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(KmsgRecord::parse(b"6,1,2,-message").is_err());
+        assert!(KmsgRecord::parse(b"x,1,2,-;message").is_err());
+        assert!(KmsgRecord::parse(b"6,1,2,?;message").is_err());
+        assert!(KmsgRecord::parse(b"6,1;message").is_err());
+        assert!(KmsgRecord::parse(b"2054,1,2,-;message").is_err());
+    }
+
+    #[test]
+    fn lost_records() {
+        let mut reader = datagram_reader(&[10, 15, 16]);
+        assert!(matches!(reader.read_event().unwrap(), Some(Event::Record(ref r)) if r.sequence == 10));
+        assert_eq!(reader.read_event().unwrap(), Some(Event::Lost(4)));
+        assert_eq!(reader.cursor().unwrap().sequence, 10);
+        assert!(matches!(reader.read_event().unwrap(), Some(Event::Record(ref r)) if r.sequence == 15));
+        assert!(matches!(reader.read_event().unwrap(), Some(Event::Record(ref r)) if r.sequence == 16));
+        assert_eq!(reader.read_event().unwrap(), None);
+    }
+
+    #[test]
+    fn filtered_records() {
+        let mut reader = datagram_reader(&[1, 2, 3, 5, 6]);
+        reader.set_filter(|record| record.sequence % 2 == 0);
+        let events = reader.events().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[0], Event::Record(ref r) if r.sequence == 2));
+        assert_eq!(events[1], Event::Lost(1));
+        assert!(matches!(events[2], Event::Record(ref r) if r.sequence == 6));
+    }
+
+    #[test]
+    fn resume_from_cursor() {
+        let mut reader = datagram_reader(&[3, 4, 5, 6]);
+        assert_eq!(reader.cursor(), None);
+        assert_eq!(reader.skip_to(4).unwrap(), Resume::Resumed);
+        assert_eq!(reader.cursor(), Some(Cursor { boot_id: "boot".to_owned(), sequence: 4 }));
+        assert_eq!(reader.read_record().unwrap().map(|r| r.sequence), Some(5));
+        assert_eq!(reader.read_record().unwrap().map(|r| r.sequence), Some(6));
+        assert_eq!(reader.cursor().unwrap().to_string(), "boot:6");
+        assert_eq!(reader.read_record().unwrap(), None);
+
+        let mut reader = datagram_reader(&[10, 11]);
+        assert_eq!(reader.skip_to(6).unwrap(), Resume::Overwritten(3));
+        assert_eq!(reader.cursor().unwrap().sequence, 6);
+        assert_eq!(reader.read_record().unwrap().map(|r| r.sequence), Some(10));
+        assert_eq!(reader.cursor().unwrap().sequence, 10);
+
+        let mut reader = datagram_reader(&[10, 11]);
+        assert_eq!(reader.skip_to(11).unwrap(), Resume::Resumed);
+        assert_eq!(reader.read_record().unwrap(), None);
+        assert_eq!(reader.cursor().unwrap().sequence, 11);
+
+        let path = ::std::env::temp_dir().join(format!("kernlog-cursor-{}", process::id()));
+        File::create(&path).unwrap();
+        let mut reader = KmsgReader::from_file(File::open(&path).unwrap());
+        fs::remove_file(&path).unwrap();
+        reader.boot_id = Some("boot".to_owned());
+        let cursor = Cursor { boot_id: "other".to_owned(), sequence: 1 };
+        assert_eq!(reader.seek_cursor(&cursor).unwrap(), Resume::BootChanged);
+        reader.boot_id = None;
+        assert_eq!(reader.seek_cursor(&cursor).err().map(|err| err.kind()), Some(io::ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn read_kmsg() {
+        let mut reader = match KmsgReader::open() {
+            Ok(reader) => reader,
+            Err(_) => return,
+        };
+        for record in reader.by_ref().take(16) {
+            record.unwrap();
+        }
+        reader.seek(Position::AfterClear).unwrap();
+        reader.seek(Position::End).unwrap();
+        reader.seek(Position::Start).unwrap();
+        assert!(reader.wait(Some(Duration::from_millis(10))).unwrap());
+    }
+}
@@ -0,0 +1,111 @@
+use std::borrow::Cow;
+use std::io::Write;
+use std::str;
+
+/// How to sanitize message contents before writing them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Sanitize {
+    /// Write messages verbatim
+    Off,
+    /// Render control characters and invalid UTF-8 bytes as `\xNN`,
+    /// the way the kernel escapes them when `/dev/kmsg` is read (default)
+    #[default]
+    Escape,
+    /// Escape like `Escape` and also neutralize `<N>` priority markers
+    /// at the start of message lines, so they can't be mistaken for
+    /// record priorities
+    Strict,
+}
+
+/// Sanitize a single message line according to `mode`
+pub(crate) fn sanitize(line: &[u8], mode: Sanitize) -> Cow<'_, [u8]> {
+    if mode == Sanitize::Off || (is_clean(line) && !(mode == Sanitize::Strict && has_priority(line))) {
+        return Cow::Borrowed(line);
+    }
+
+    let mut buf = Vec::with_capacity(line.len() + 16);
+    let mut rest = line;
+
+    if mode == Sanitize::Strict && has_priority(line) {
+        escape(&mut buf, b'<');
+        rest = &rest[1..];
+    }
+
+    loop {
+        let (valid, invalid) = match str::from_utf8(rest) {
+            Ok(valid) => (valid, &b""[..]),
+            Err(err) => {
+                let (valid, invalid) = rest.split_at(err.valid_up_to());
+                let len = err.error_len().unwrap_or(invalid.len());
+                rest = &invalid[len..];
+                (str::from_utf8(valid).unwrap(), &invalid[..len])
+            }
+        };
+
+        for c in valid.chars() {
+            if is_control(c) {
+                let mut bytes = [0; 4];
+                for &b in c.encode_utf8(&mut bytes).as_bytes() {
+                    escape(&mut buf, b);
+                }
+            } else {
+                let mut bytes = [0; 4];
+                buf.extend_from_slice(c.encode_utf8(&mut bytes).as_bytes());
+            }
+        }
+
+        for &b in invalid {
+            escape(&mut buf, b);
+        }
+
+        if invalid.is_empty() {
+            return Cow::Owned(buf);
+        }
+    }
+}
+
+fn is_clean(line: &[u8]) -> bool {
+    str::from_utf8(line).map(|s| !s.chars().any(is_control)).unwrap_or(false)
+}
+
+fn is_control(c: char) -> bool {
+    c.is_control() && c != '\t'
+}
+
+/// Check if line starts with `<N>` priority marker
+fn has_priority(line: &[u8]) -> bool {
+    line.first() == Some(&b'<') && line[1..].iter()
+        .position(|b| !b.is_ascii_digit())
+        .is_some_and(|pos| pos > 0 && line[pos + 1] == b'>')
+}
+
+fn escape(buf: &mut Vec<u8>, b: u8) {
+    write!(buf, "\\x{:02x}", b).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sanitize, Sanitize};
+
+    fn check(line: &[u8], mode: Sanitize) -> String {
+        String::from_utf8(sanitize(line, mode).into_owned()).unwrap()
+    }
+
+    #[test]
+    fn escape_controls() {
+        assert_eq!(check(b"plain\ttext \xd1\x8f", Sanitize::Escape), "plain\ttext \u{44f}");
+        assert_eq!(check(b"nul\0 esc\x1b[31m cr\r nl\n", Sanitize::Escape), "nul\\x00 esc\\x1b[31m cr\\x0d nl\\x0a");
+        assert_eq!(check(b"bad \xff\xd1 end", Sanitize::Escape), "bad \\xff\\xd1 end");
+        assert_eq!(check("csi \u{9b}".as_bytes(), Sanitize::Escape), "csi \\xc2\\x9b");
+        assert_eq!(sanitize(b"raw\x1b", Sanitize::Off).into_owned(), b"raw\x1b");
+    }
+
+    #[test]
+    fn neutralize_priorities() {
+        assert_eq!(check(b"<0>fake panic", Sanitize::Escape), "<0>fake panic");
+        assert_eq!(check(b"<0>fake panic", Sanitize::Strict), "\\x3c0>fake panic");
+        assert_eq!(check(b"<b>bold</b>", Sanitize::Strict), "<b>bold</b>");
+        assert_eq!(check(b"<>", Sanitize::Strict), "<>");
+        assert_eq!(check(b"<12", Sanitize::Strict), "<12");
+    }
+}
@@ -0,0 +1,224 @@
+use std::ffi::CStr;
+use std::io::{self, Write};
+use std::mem;
+use std::os::unix::net::{UnixDatagram, UnixStream};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use libc;
+
+use Priority;
+
+/// Header format of messages written to the syslog socket
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyslogFormat {
+    /// BSD syslog, `<PRI>Mmm dd hh:mm:ss ident[pid]: message`,
+    /// the format of libc's `syslog()` (default)
+    #[default]
+    Rfc3164,
+    /// IETF syslog, `<PRI>1 timestamp hostname app-name procid - - message`
+    /// with RFC 3339 timestamps
+    Rfc5424,
+}
+
+/// Format message header for the syslog socket at `time`
+pub(crate) fn header(format: SyslogFormat, priority: Priority, ident: Option<&str>, pid: bool, time: SystemTime) -> Vec<u8> {
+    let mut header = Vec::new();
+    let (secs, micros) = match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => (since.as_secs() as libc::time_t, since.subsec_micros()),
+        Err(_) => (0, 0),
+    };
+
+    match format {
+        SyslogFormat::Rfc3164 => {
+            write!(header, "<{}>{} ", priority.code(), strftime(secs, b"%b %e %H:%M:%S\0")).unwrap();
+            if let Some(ident) = ident {
+                header.extend_from_slice(ident.as_bytes());
+                if pid {
+                    write!(header, "[{}]", process::id()).unwrap();
+                }
+                header.extend_from_slice(b": ");
+            }
+        }
+        SyslogFormat::Rfc5424 => {
+            let offset = strftime(secs, b"%z\0");
+            let (hours, minutes) = offset.split_at(offset.len().min(3));
+            write!(header, "<{}>1 {}.{:06}{}:{} {} {} ", priority.code(),
+                   strftime(secs, b"%Y-%m-%dT%H:%M:%S\0"), micros, hours, minutes,
+                   hostname().unwrap_or_else(|| "-".to_owned()),
+                   ident.filter(|ident| !ident.is_empty()).unwrap_or("-")).unwrap();
+            if pid {
+                write!(header, "{} - - ", process::id()).unwrap();
+            } else {
+                header.extend_from_slice(b"- - - ");
+            }
+        }
+    }
+    header
+}
+
+/// Format time in local time zone with strftime `format`
+fn strftime(secs: libc::time_t, format: &[u8]) -> String {
+    let mut buf = [0u8; 64];
+    let len = unsafe {
+        let mut tm: libc::tm = mem::zeroed();
+        libc::localtime_r(&secs, &mut tm);
+        libc::strftime(buf.as_mut_ptr() as *mut libc::c_char, buf.len(),
+                       format.as_ptr() as *const libc::c_char, &tm)
+    };
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+fn hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    if unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) } != 0 {
+        return None;
+    }
+    let name = CStr::from_bytes_until_nul(&buf).ok()?.to_string_lossy().into_owned();
+    if name.is_empty() { None } else { Some(name) }
+}
+
+#[derive(Debug)]
+enum Connection {
+    Datagram(UnixDatagram),
+    Stream(UnixStream),
+}
+
+impl Connection {
+    /// Connect to datagram socket at `path`, or to stream one
+    /// if the syslog daemon listens on a stream socket
+    fn open(path: &Path) -> io::Result<Connection> {
+        let socket = UnixDatagram::unbound()?;
+        match socket.connect(path) {
+            Ok(()) => Ok(Connection::Datagram(socket)),
+            Err(ref err) if err.raw_os_error() == Some(libc::EPROTOTYPE) => {
+                Ok(Connection::Stream(UnixStream::connect(path)?))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn send(&mut self, message: &[u8]) -> io::Result<()> {
+        match *self {
+            // datagrams are framed by themselves
+            Connection::Datagram(ref socket) => socket.send(message.strip_suffix(b"\n").unwrap_or(message)).map(drop),
+            // stream messages are newline terminated (RFC 6587 non-transparent framing)
+            Connection::Stream(ref mut stream) => stream.write_all(message),
+        }
+    }
+}
+
+/// Syslog daemon socket writer, sending each write as a single message
+///
+/// The socket is reconnected once if sending fails because the daemon
+/// was restarted and recreated its socket.
+#[derive(Debug)]
+pub(crate) struct SyslogSocket {
+    path: PathBuf,
+    connection: Option<Connection>,
+}
+
+impl SyslogSocket {
+    pub(crate) fn connect<P: AsRef<Path>>(path: P) -> io::Result<SyslogSocket> {
+        let path = path.as_ref().to_path_buf();
+        let connection = Connection::open(&path)?;
+        Ok(SyslogSocket { path, connection: Some(connection) })
+    }
+}
+
+impl Write for SyslogSocket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(ref mut connection) = self.connection {
+            match connection.send(buf) {
+                Ok(()) => return Ok(buf.len()),
+                Err(ref err) if is_disconnect(err) => (),
+                Err(err) => return Err(err),
+            }
+        }
+
+        self.connection = None;
+        let mut connection = Connection::open(&self.path)?;
+        connection.send(buf)?;
+        self.connection = Some(connection);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn is_disconnect(err: &io::Error) -> bool {
+    matches!(err.kind(), io::ErrorKind::ConnectionRefused | io::ErrorKind::ConnectionReset
+        | io::ErrorKind::NotConnected | io::ErrorKind::BrokenPipe | io::ErrorKind::NotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::io::{Read, Write};
+    use std::os::unix::net::{UnixDatagram, UnixListener};
+    use std::process;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{header, SyslogFormat, SyslogSocket};
+    use {Facility, Priority, Severity};
+
+    #[test]
+    fn headers() {
+        let priority = Priority::new(Facility::Daemon, Severity::Notice);
+        let time = UNIX_EPOCH + Duration::new(1_700_000_000, 250_000_000);
+
+        let rfc3164 = String::from_utf8(header(SyslogFormat::Rfc3164, priority, Some("hook"), true, time)).unwrap();
+        assert!(rfc3164.starts_with("<29>Nov 1"), "{}", rfc3164);
+        assert!(rfc3164.ends_with(&format!(":20 hook[{}]: ", process::id())), "{}", rfc3164);
+        assert_eq!(rfc3164.len(), "<29>Nov 14 22:13:20 hook[]: ".len() + process::id().to_string().len());
+
+        let rfc5424 = String::from_utf8(header(SyslogFormat::Rfc5424, priority, Some("hook"), false, time)).unwrap();
+        assert!(rfc5424.starts_with("<29>1 2023-11-1"), "{}", rfc5424);
+        assert!(rfc5424.contains(":20.250000"), "{}", rfc5424);
+        assert!(rfc5424.ends_with(" hook - - - "), "{}", rfc5424);
+        assert_eq!(rfc5424.split(' ').nth(1).map(|stamp| stamp.len()), Some("2023-11-14T22:13:20.250000+00:00".len()));
+
+        let anonymous = String::from_utf8(header(SyslogFormat::Rfc5424, priority, None, false, time)).unwrap();
+        assert!(anonymous.ends_with(" - - - - "), "{}", anonymous);
+    }
+
+    #[test]
+    fn reconnect_datagram() {
+        let path = env::temp_dir().join(format!("kernlog-syslog-dgram-{}", process::id()));
+        let _ = fs::remove_file(&path);
+        let server = UnixDatagram::bind(&path).unwrap();
+        let mut socket = SyslogSocket::connect(&path).unwrap();
+        socket.write_all(b"<14>first\n").unwrap();
+
+        // syslog daemon restart recreates the socket
+        drop(server);
+        fs::remove_file(&path).unwrap();
+        let server = UnixDatagram::bind(&path).unwrap();
+        socket.write_all(b"<14>second\n").unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let mut buf = [0; 64];
+        let len = server.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"<14>second");
+    }
+
+    #[test]
+    fn stream_socket() {
+        let path = env::temp_dir().join(format!("kernlog-syslog-stream-{}", process::id()));
+        let _ = fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let mut socket = SyslogSocket::connect(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        socket.write_all(b"<14>one\n").unwrap();
+        socket.write_all(b"<14>two\n").unwrap();
+        drop(socket);
+
+        let mut contents = String::new();
+        listener.accept().unwrap().0.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "<14>one\n<14>two\n");
+    }
+}